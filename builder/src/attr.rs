@@ -0,0 +1,568 @@
+use proc_macro2::TokenStream;
+use syn::{Attribute, Error, Expr, Ident, Lit, LitStr, Meta, MetaList, NestedMeta, Path, Result};
+
+/// Options given through `#[builder(...)]` on the struct itself.
+#[derive(Default)]
+pub struct StructAttrs {
+    /// Track required fields in the builder's type instead of at runtime.
+    pub typestate: bool,
+    /// Error type returned by `build()` instead of the generated one. It must
+    /// implement `From` for the generated error.
+    pub error: Option<Path>,
+    /// `build_fn(validate = "...")`: called with the builder before the struct
+    /// is constructed, returning `Result<(), String>`.
+    pub validate: Option<Path>,
+    /// `setter(into)`: the default for every field's setters.
+    pub into: bool,
+    /// How setters and `build()` take the builder.
+    pub pattern: Pattern,
+    /// Derive `Serialize` and `Deserialize` for the builder.
+    pub serde: bool,
+    /// `group(...)`: constraints on which fields are set together.
+    pub groups: Vec<Group>,
+    /// `derive(...)`: traits derived for the builder. `Debug` is implemented
+    /// by hand so that unset fields show as `<unset>`.
+    pub derives: Vec<Path>,
+    /// Generate code that only uses `core` and `alloc`.
+    pub no_std: bool,
+    /// `const`: `const fn` setters and a `build_const()` usable in constants.
+    pub constant: bool,
+    /// `flattenable`: other builders in the same crate can `flatten` this
+    /// one, through the hidden items that come with it. Holds the key, where
+    /// it is reported if the builder cannot be flattened.
+    pub flattenable: Option<Path>,
+}
+
+pub struct Group {
+    pub name: LitStr,
+    pub rule: Rule,
+    pub fields: Vec<Ident>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Rule {
+    /// `exactly_one(a, b)`: one of the fields is set, never more.
+    ExactlyOne,
+    /// `requires_all(a, b)`: either all of the fields are set or none.
+    RequiresAll,
+}
+
+#[derive(Clone, Copy, Default, PartialEq)]
+pub enum Pattern {
+    /// `&mut self -> &mut Self` setters; `build(&self)` clones.
+    #[default]
+    Mutable,
+    /// `self -> Self` setters; `build(self)` moves.
+    Owned,
+    /// `&self -> Self` setters that clone; `build(&self)` clones.
+    Immutable,
+}
+
+/// Options given through `#[builder(...)]` on a field.
+#[derive(Default)]
+pub struct FieldAttrs {
+    /// Name of the setter that appends one element at a time.
+    pub each: Option<Ident>,
+    /// Value used by `build()` when the field was never set.
+    pub default: Option<DefaultValue>,
+    /// `setter(into)`: setters accept anything that converts into the type.
+    pub into: Option<bool>,
+    /// `setter(strip_option = false)`: an `Option<T>` field's setter takes
+    /// `Option<T>` rather than `T`.
+    pub strip_option: Option<bool>,
+    /// `setter(boxed)`: setters of a `Box`, `Arc` or `Rc` of a trait object
+    /// take any implementation of the trait and wrap it.
+    pub boxed: Option<bool>,
+    /// `setter(try_into)`: a `try_<field>` setter taking anything that
+    /// converts into the type with `TryInto`.
+    pub try_into: Option<Fallible>,
+    /// How `merge()` combines this field with the other builder's.
+    pub merge: Option<Merge>,
+    /// Name of the field in the builder's serialized form.
+    pub rename: Option<LitStr>,
+    /// Name of the field's setter and builder field, mainly for the
+    /// positional fields of tuple structs.
+    pub name: Option<Ident>,
+    /// Environment variable read by `from_env()`.
+    pub env: Option<LitStr>,
+    /// Set the field through its own builder, which `build()` builds.
+    pub sub_builder: bool,
+    /// `flatten`: like `sub_builder`, with the nested builder's setters on
+    /// this builder. The field's struct must be `flattenable`, and so in the
+    /// same crate.
+    pub flatten: bool,
+    /// `alias = "..."`: former names of the field's setter, kept as
+    /// deprecated setters.
+    pub aliases: Vec<Ident>,
+    /// The field's `///` docs, repeated on its setters.
+    pub docs: Vec<Attribute>,
+    /// The field's `#[deprecated]`, repeated on its setters.
+    pub deprecated: Option<Attribute>,
+}
+
+/// When the `try_<field>` setter reports a failed conversion.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Fallible {
+    /// `setter(try_into)`: the setter returns the conversion's error.
+    Immediate,
+    /// `setter(try_into = "deferred")`: the setter returns the builder as
+    /// usual, and `build()` returns the error.
+    Deferred,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Merge {
+    /// The other builder's value wins if it is set.
+    Replace,
+    /// `merge = "append"` on `each` fields: the other builder's items are
+    /// added to this one's.
+    Append,
+}
+
+pub enum DefaultValue {
+    /// `#[builder(default)]`
+    Trait,
+    /// `#[builder(default = "...")]`
+    Expr(Box<Expr>),
+}
+
+/// Errors collected while parsing, so that all of them are reported at once.
+#[derive(Default)]
+pub struct Errors(Option<Error>);
+
+impl Errors {
+    pub fn push(&mut self, error: Error) {
+        match &mut self.0 {
+            Some(errors) => errors.combine(error),
+            None => self.0 = Some(error),
+        }
+    }
+
+    pub fn to_compile_error(&self) -> TokenStream {
+        match &self.0 {
+            Some(errors) => errors.to_compile_error(),
+            None => TokenStream::new(),
+        }
+    }
+}
+
+// The keys accepted at each level, with how they are written, for reporting
+// misspelled and malformed ones.
+
+const STRUCT_KEYS: &[(&str, &str)] = &[
+    ("typestate", "typestate"),
+    ("serde", "serde"),
+    ("error", "error = \"...\""),
+    ("pattern", "pattern = \"...\""),
+    ("build_fn", "build_fn(validate = \"...\")"),
+    ("group", "group(...)"),
+    ("setter", "setter(into)"),
+    ("derive", "derive(Debug, Clone, PartialEq)"),
+    ("no_std", "no_std"),
+    ("const", "const"),
+    ("flattenable", "flattenable"),
+];
+
+const FIELD_KEYS: &[(&str, &str)] = &[
+    ("each", "each = \"...\""),
+    ("default", "default` or `default = \"...\""),
+    ("sub_builder", "sub_builder"),
+    ("flatten", "flatten"),
+    ("name", "name = \"...\""),
+    ("env", "env = \"...\""),
+    ("rename", "rename = \"...\""),
+    ("merge", "merge = \"...\""),
+    ("alias", "alias = \"...\""),
+    ("setter", "setter(...)"),
+];
+
+const STRUCT_SETTER_KEYS: &[(&str, &str)] = &[("into", "into")];
+
+const FIELD_SETTER_KEYS: &[(&str, &str)] = &[
+    ("into", "into"),
+    ("strip_option", "strip_option = false"),
+    ("boxed", "boxed` or `boxed = false"),
+    ("try_into", "try_into` or `try_into = \"deferred\""),
+];
+
+const BUILD_FN_KEYS: &[(&str, &str)] = &[("validate", "validate = \"...\"")];
+
+pub fn struct_attrs(attrs: &[Attribute], errors: &mut Errors) -> StructAttrs {
+    let mut out = StructAttrs::default();
+    let mut pattern = None;
+
+    for list in builder_lists(attrs, errors) {
+        for nested in &list.nested {
+            if let Err(error) = struct_attr(nested, &mut out, &mut pattern, errors) {
+                errors.push(error);
+            }
+        }
+    }
+
+    out.pattern = match pattern {
+        Some((lit, pattern)) if out.typestate && pattern != Pattern::Owned => {
+            errors.push(Error::new_spanned(
+                lit,
+                "typestate builders always use the owned pattern",
+            ));
+            Pattern::Owned
+        }
+        Some((lit, pattern)) if out.constant && pattern != Pattern::Owned => {
+            errors.push(Error::new_spanned(
+                lit,
+                "const builders always use the owned pattern",
+            ));
+            Pattern::Owned
+        }
+        Some((_, pattern)) => pattern,
+        None if out.typestate || out.constant => Pattern::Owned,
+        None => Pattern::default(),
+    };
+
+    out
+}
+
+fn struct_attr(
+    nested: &NestedMeta,
+    out: &mut StructAttrs,
+    pattern: &mut Option<(LitStr, Pattern)>,
+    errors: &mut Errors,
+) -> Result<()> {
+    match nested {
+        NestedMeta::Meta(Meta::Path(path)) if path.is_ident("typestate") => {
+            out.typestate = true;
+        }
+        NestedMeta::Meta(Meta::Path(path)) if path.is_ident("const") => {
+            out.constant = true;
+        }
+        NestedMeta::Meta(Meta::Path(path)) if path.is_ident("no_std") => {
+            out.no_std = true;
+        }
+        NestedMeta::Meta(Meta::Path(path)) if path.is_ident("flattenable") => {
+            out.flattenable = Some(path.clone());
+        }
+        NestedMeta::Meta(Meta::Path(path)) if path.is_ident("serde") => {
+            if !cfg!(feature = "serde") {
+                return Err(Error::new_spanned(
+                    path,
+                    "`builder(serde)` requires the `serde` feature of derive_builder",
+                ));
+            }
+            out.serde = true;
+        }
+        NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("error") => {
+            out.error = Some(lit_str(&nv.lit)?.parse()?);
+        }
+        NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("pattern") => {
+            let lit = lit_str(&nv.lit)?;
+            let value = match lit.value().as_str() {
+                "mutable" => Pattern::Mutable,
+                "owned" => Pattern::Owned,
+                "immutable" => Pattern::Immutable,
+                _ => {
+                    return Err(Error::new_spanned(
+                        lit,
+                        "expected \"mutable\", \"owned\" or \"immutable\"",
+                    ))
+                }
+            };
+            *pattern = Some((lit.clone(), value));
+        }
+        NestedMeta::Meta(Meta::List(build_fn)) if build_fn.path.is_ident("build_fn") => {
+            for nested in &build_fn.nested {
+                match nested {
+                    NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("validate") => {
+                        match lit_str(&nv.lit).and_then(LitStr::parse) {
+                            Ok(path) => out.validate = Some(path),
+                            Err(error) => errors.push(error),
+                        }
+                    }
+                    _ => errors.push(unknown(nested, BUILD_FN_KEYS)),
+                }
+            }
+        }
+        NestedMeta::Meta(Meta::List(group)) if group.path.is_ident("group") => {
+            out.groups.push(parse_group(group)?);
+        }
+        NestedMeta::Meta(Meta::List(setter)) if setter.path.is_ident("setter") => {
+            for nested in &setter.nested {
+                match flag(nested, "into") {
+                    Ok(Some(into)) => out.into = into,
+                    Ok(None) => errors.push(unknown(nested, STRUCT_SETTER_KEYS)),
+                    Err(error) => errors.push(error),
+                }
+            }
+        }
+        NestedMeta::Meta(Meta::List(derive)) if derive.path.is_ident("derive") => {
+            for nested in &derive.nested {
+                match nested {
+                    NestedMeta::Meta(Meta::Path(path)) => out.derives.push(path.clone()),
+                    _ => errors.push(Error::new_spanned(nested, "expected a trait to derive")),
+                }
+            }
+        }
+        _ => return Err(unknown(nested, STRUCT_KEYS)),
+    }
+    Ok(())
+}
+
+pub fn field_attrs(attrs: &[Attribute], errors: &mut Errors) -> FieldAttrs {
+    let mut out = FieldAttrs {
+        docs: attrs
+            .iter()
+            .filter(|attr| attr.path.is_ident("doc"))
+            .cloned()
+            .collect(),
+        deprecated: attrs
+            .iter()
+            .find(|attr| attr.path.is_ident("deprecated"))
+            .cloned(),
+        ..FieldAttrs::default()
+    };
+
+    for list in builder_lists(attrs, errors) {
+        for nested in &list.nested {
+            if let Err(error) = field_attr(nested, &mut out, errors) {
+                errors.push(error);
+            }
+        }
+    }
+
+    out
+}
+
+fn field_attr(nested: &NestedMeta, out: &mut FieldAttrs, errors: &mut Errors) -> Result<()> {
+    match nested {
+        NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("each") => {
+            out.each = Some(lit_str(&nv.lit)?.parse()?);
+        }
+        NestedMeta::Meta(Meta::Path(path)) if path.is_ident("default") => {
+            out.default = Some(DefaultValue::Trait);
+        }
+        NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("default") => {
+            out.default = Some(DefaultValue::Expr(Box::new(lit_str(&nv.lit)?.parse()?)));
+        }
+        NestedMeta::Meta(Meta::Path(path)) if path.is_ident("sub_builder") => {
+            out.sub_builder = true;
+        }
+        NestedMeta::Meta(Meta::Path(path)) if path.is_ident("flatten") => {
+            out.flatten = true;
+        }
+        NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("name") => {
+            out.name = Some(lit_str(&nv.lit)?.parse()?);
+        }
+        NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("env") => {
+            out.env = Some(lit_str(&nv.lit)?.clone());
+        }
+        NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("alias") => {
+            out.aliases.push(lit_str(&nv.lit)?.parse()?);
+        }
+        NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("rename") => {
+            out.rename = Some(lit_str(&nv.lit)?.clone());
+        }
+        NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("merge") => {
+            let lit = lit_str(&nv.lit)?;
+            let merge = match lit.value().as_str() {
+                "replace" => Merge::Replace,
+                "append" => Merge::Append,
+                _ => {
+                    return Err(Error::new_spanned(
+                        lit,
+                        "expected \"replace\" or \"append\"",
+                    ))
+                }
+            };
+            out.merge = Some(merge);
+        }
+        NestedMeta::Meta(Meta::List(setter)) if setter.path.is_ident("setter") => {
+            for nested in &setter.nested {
+                let parsed = field_setter_attr(nested, out);
+                if let Err(error) = parsed {
+                    errors.push(error);
+                }
+            }
+        }
+        _ => return Err(unknown(nested, FIELD_KEYS)),
+    }
+    Ok(())
+}
+
+/// One of the options in a field's `setter(...)`.
+fn field_setter_attr(nested: &NestedMeta, out: &mut FieldAttrs) -> Result<()> {
+    if let Some(into) = flag(nested, "into")? {
+        out.into = Some(into);
+    } else if let Some(strip) = flag(nested, "strip_option")? {
+        out.strip_option = Some(strip);
+    } else if let Some(boxed) = flag(nested, "boxed")? {
+        out.boxed = Some(boxed);
+    } else if let Some(fallible) = fallible(nested)? {
+        out.try_into = Some(fallible);
+    } else {
+        return Err(unknown(nested, FIELD_SETTER_KEYS));
+    }
+    Ok(())
+}
+
+/// The error for a key that is not one of `known`, or is one but written
+/// the wrong way. Misspelled keys get a suggestion and point at the key.
+fn unknown(nested: &NestedMeta, known: &[(&str, &str)]) -> Error {
+    let path = match nested {
+        NestedMeta::Meta(meta) => meta.path(),
+        NestedMeta::Lit(lit) => return Error::new_spanned(lit, "expected a builder attribute"),
+    };
+    let key = path
+        .segments
+        .iter()
+        .map(|segment| segment.ident.to_string())
+        .collect::<Vec<_>>()
+        .join("::");
+
+    if let Some((_, usage)) = known.iter().find(|(name, _)| *name == key) {
+        return Error::new_spanned(nested, format!("expected `{}`", usage));
+    }
+
+    let closest = known
+        .iter()
+        .map(|(name, _)| (edit_distance(&key, name), *name))
+        .filter(|(distance, name)| *distance <= 2 && *distance < key.len().max(name.len()))
+        .min();
+    match closest {
+        Some((_, name)) => Error::new_spanned(
+            path,
+            format!(
+                "unrecognized builder attribute `{}`; did you mean `{}`?",
+                key, name,
+            ),
+        ),
+        None => Error::new_spanned(path, format!("unrecognized builder attribute `{}`", key)),
+    }
+}
+
+/// Levenshtein distance between `a` and `b`.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let above = row[j + 1];
+            row[j + 1] = if ca == *cb {
+                diagonal
+            } else {
+                1 + diagonal.min(above).min(row[j])
+            };
+            diagonal = above;
+        }
+    }
+    row[b.len()]
+}
+
+/// Parses `group(name = "...", exactly_one(a, b))`.
+fn parse_group(list: &MetaList) -> Result<Group> {
+    let mut name = None;
+    let mut rule = None;
+
+    for nested in &list.nested {
+        match nested {
+            NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("name") => {
+                name = Some(lit_str(&nv.lit)?.clone());
+            }
+            NestedMeta::Meta(Meta::List(fields))
+                if rule.is_none()
+                    && (fields.path.is_ident("exactly_one")
+                        || fields.path.is_ident("requires_all")) =>
+            {
+                let kind = if fields.path.is_ident("exactly_one") {
+                    Rule::ExactlyOne
+                } else {
+                    Rule::RequiresAll
+                };
+                let idents = fields
+                    .nested
+                    .iter()
+                    .map(|nested| match nested {
+                        NestedMeta::Meta(Meta::Path(path)) => match path.get_ident() {
+                            Some(ident) => Ok(ident.clone()),
+                            None => Err(Error::new_spanned(path, "expected a field name")),
+                        },
+                        _ => Err(Error::new_spanned(nested, "expected a field name")),
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                if idents.len() < 2 {
+                    return Err(Error::new_spanned(
+                        fields,
+                        "a group needs at least two fields",
+                    ));
+                }
+                rule = Some((kind, idents));
+            }
+            _ => return Err(Error::new_spanned(
+                nested,
+                "expected `name = \"...\"` and one of `exactly_one(...)` or `requires_all(...)`",
+            )),
+        }
+    }
+
+    match (name, rule) {
+        (Some(name), Some((rule, fields))) => Ok(Group { name, rule, fields }),
+        _ => Err(Error::new_spanned(
+            list,
+            "expected `group(name = \"...\", exactly_one(...))` or \
+             `group(name = \"...\", requires_all(...))`",
+        )),
+    }
+}
+
+fn builder_lists(attrs: &[Attribute], errors: &mut Errors) -> Vec<MetaList> {
+    let mut lists = Vec::new();
+
+    for attr in attrs {
+        if !attr.path.is_ident("builder") {
+            continue;
+        }
+        match attr.parse_meta() {
+            Ok(Meta::List(list)) => lists.push(list),
+            Ok(meta) => errors.push(Error::new_spanned(meta, "expected `builder(...)`")),
+            Err(error) => errors.push(error),
+        }
+    }
+
+    lists
+}
+
+/// Matches `name` or `name = true/false`.
+fn flag(nested: &NestedMeta, name: &str) -> Result<Option<bool>> {
+    match nested {
+        NestedMeta::Meta(Meta::Path(path)) if path.is_ident(name) => Ok(Some(true)),
+        NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident(name) => match &nv.lit {
+            Lit::Bool(lit) => Ok(Some(lit.value)),
+            lit => Err(Error::new_spanned(lit, "expected `true` or `false`")),
+        },
+        _ => Ok(None),
+    }
+}
+
+/// `try_into` or `try_into = "deferred"`.
+fn fallible(nested: &NestedMeta) -> Result<Option<Fallible>> {
+    match nested {
+        NestedMeta::Meta(Meta::Path(path)) if path.is_ident("try_into") => {
+            Ok(Some(Fallible::Immediate))
+        }
+        NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("try_into") => {
+            let lit = lit_str(&nv.lit)?;
+            match lit.value().as_str() {
+                "deferred" => Ok(Some(Fallible::Deferred)),
+                _ => Err(Error::new_spanned(lit, "expected \"deferred\"")),
+            }
+        }
+        _ => Ok(None),
+    }
+}
+
+fn lit_str(lit: &Lit) -> Result<&LitStr> {
+    match lit {
+        Lit::Str(lit) => Ok(lit),
+        lit => Err(Error::new_spanned(lit, "expected string literal")),
+    }
+}
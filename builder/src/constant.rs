@@ -0,0 +1,114 @@
+// `build_const()` for builders with `#[builder(const)]`.
+//
+// In this mode `builder()` and the setters are `const fn`, and
+// `build_const()` checks the builder the way `build()` does but panics instead
+// of returning an error, which in a constant is a compile-time error. Only
+// field types that can be moved around in const fn work, which rules out
+// anything with a destructor. The methods that would drop such a field report
+// it at the field's type, and build_const() at the struct's name since it
+// takes the whole builder.
+
+use crate::attr::Rule;
+use crate::expand::{Kind, Struct};
+use proc_macro2::TokenStream;
+use quote::{quote, quote_spanned};
+
+pub fn expand(s: &Struct) -> TokenStream {
+    // Without const setters for every field, build_const() could not be
+    // called in a constant either.
+    if s.constness().is_none() {
+        return TokenStream::new();
+    }
+
+    let core = s.std.core();
+    let vis = s.vis;
+    let ident = s.ident;
+    let builder = &s.builder;
+    let (impl_generics, ty_generics, where_clause) = s.generics.split_for_impl();
+
+    let group_checks = s.attrs.groups.iter().map(|group| {
+        let count = group
+            .fields
+            .iter()
+            .map(|member| quote!(self.#member.is_some() as usize));
+        let n = group.fields.len();
+        let fields = group
+            .fields
+            .iter()
+            .map(|member| format!("`{}`", member))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let (check, message) = match group.rule {
+            Rule::ExactlyOne => (
+                quote!(set != 1),
+                format!(
+                    "{}: exactly one of {} must be set (group \"{}\")",
+                    builder,
+                    fields,
+                    group.name.value(),
+                ),
+            ),
+            Rule::RequiresAll => (
+                quote!(set != 0 && set != #n),
+                format!(
+                    "{}: {} must be set together (group \"{}\")",
+                    builder,
+                    fields,
+                    group.name.value(),
+                ),
+            ),
+        };
+        quote! {{
+            let set = #(#count)+*;
+            if #check {
+                #core::panic!(#message);
+            }
+        }}
+    });
+
+    let bindings = s.fields.iter().map(|f| {
+        let name = &f.ident;
+        let value = match (&f.kind, f.default_value()) {
+            (Kind::Required, None) => {
+                let message = format!(
+                    "{}: `{}` must be set before build_const()",
+                    builder,
+                    name.to_string().trim_start_matches("r#"),
+                );
+                quote! {
+                    match self.#name {
+                        #core::option::Option::Some(value) => value,
+                        #core::option::Option::None => #core::panic!(#message),
+                    }
+                }
+            }
+            _ => f.value(quote!(self.#name)),
+        };
+        quote!(let #name = #value;)
+    });
+
+    let construct = s.construct();
+    let doc = format!(
+        "Builds the [`{}`] in a const context, panicking if a required field is \
+         missing. In a `const` item that panic is a compile-time error.",
+        ident,
+    );
+
+    // A builder holding a field with a destructor cannot be dropped here,
+    // which is reported at the struct's name.
+    let build_const = quote_spanned! {ident.span()=>
+        #vis const fn build_const(self) -> #ident #ty_generics {
+            #(#group_checks)*
+            #(#bindings)*
+            #construct
+        }
+    };
+
+    quote! {
+        impl #impl_generics #builder #ty_generics #where_clause {
+            #[doc = #doc]
+            #[allow(deprecated)]
+            #build_const
+        }
+    }
+}
@@ -0,0 +1,161 @@
+// Documentation of the generated builder.
+//
+// Setters repeat the `///` docs of their field, followed by whether the field
+// is required, and carry its `#[deprecated]` along. The builder itself, its
+// constructor and `build()` get a short summary naming what they build.
+
+use crate::attr::{DefaultValue, Fallible};
+use crate::expand::{Field, Kind, Struct, Target};
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{Ident, Type};
+
+/// The attributes of a setter of the field: its docs, what happens when it is
+/// never called, and `#[deprecated]`.
+pub fn setter(f: &Field) -> TokenStream {
+    taking(f, f.setter_ty())
+}
+
+/// The attributes of a setter of the field whose argument is stored as a
+/// `ty`, which also say how the argument is wrapped for a `Box<dyn Trait>`.
+pub fn taking(f: &Field, ty: &Type) -> TokenStream {
+    let docs = &f.attrs.docs;
+    let note = format!(" {}", requirement(f));
+    let separator = if docs.is_empty() {
+        None
+    } else {
+        Some(quote!(#[doc = ""]))
+    };
+    let wrapped = f.wrapper(ty).map(|(wrapper, _)| {
+        let wrapper = &wrapper.segments.last().unwrap().ident;
+        let doc = format!(
+            " Takes any implementation of the trait and puts it in a new `{0}`. An \
+             argument that is already a `{0}` is wrapped again if the trait is \
+             implemented for it.",
+            wrapper,
+        );
+        quote! {
+            #[doc = ""]
+            #[doc = #doc]
+        }
+    });
+    let deprecated = &f.attrs.deprecated;
+    quote! {
+        #(#docs)*
+        #separator
+        #[doc = #note]
+        #wrapped
+        #deprecated
+    }
+}
+
+/// The attributes of the field's `try_<field>` setter.
+pub fn try_setter(f: &Field, fallible: Fallible) -> TokenStream {
+    let conversion = match fallible {
+        Fallible::Immediate => {
+            " Converts the value with `TryInto`, returning the error if that fails."
+        }
+        Fallible::Deferred => {
+            " Converts the value with `TryInto`. If that fails the field is left as it \
+             was and `build()` returns the error, unless the field is set or unset again."
+        }
+    };
+    let setter = setter(f);
+    quote! {
+        #setter
+        #[doc = ""]
+        #[doc = #conversion]
+    }
+}
+
+/// The attributes of `alias`, a former name of the field's setter `name`.
+pub fn alias(f: &Field, name: &Ident) -> TokenStream {
+    let doc = format!(" Former name of [`{}`](Self::{}).", unraw(name), name);
+    let note = format!("renamed to `{}`", unraw(name));
+    let docs = &f.attrs.docs;
+    quote! {
+        #[doc = #doc]
+        #[doc = ""]
+        #(#docs)*
+        #[deprecated(note = #note)]
+    }
+}
+
+/// The attributes of a setter such as `extend_<field>` that is only described
+/// by `summary`.
+pub fn helper(f: &Field, summary: &str) -> TokenStream {
+    let doc = format!(" {} `{}`.", summary, unraw(&f.ident));
+    let deprecated = &f.attrs.deprecated;
+    quote! {
+        #[doc = #doc]
+        #deprecated
+    }
+}
+
+/// What `build()` does with the field if it was never set.
+fn requirement(f: &Field) -> &'static str {
+    match (&f.kind, &f.attrs.default) {
+        (_, Some(DefaultValue::Trait)) => "Optional; defaults to `Default::default()`.",
+        (_, Some(DefaultValue::Expr(_))) => "Optional; has a default value.",
+        (Kind::Required, None) => "Required.",
+        (Kind::Optional(_), None) => "Optional; defaults to `None`.",
+        (Kind::Each(_), None) => "Optional; defaults to an empty collection.",
+        (Kind::Sub(..), None) => {
+            "Optional; set through its own builder, whose required fields must be set."
+        }
+    }
+}
+
+/// The docs of the builder struct.
+pub fn builder(s: &Struct) -> TokenStream {
+    let summary = format!(" Builder for {}.", target(s));
+    let required: Vec<String> = s
+        .fields
+        .iter()
+        .filter(|f| f.is_required())
+        .map(|f| format!("`{}`", unraw(&f.ident)))
+        .collect();
+    let required = if required.is_empty() {
+        " Every field is optional.".to_owned()
+    } else {
+        format!(" Required fields: {}.", required.join(", "))
+    };
+    quote! {
+        #[doc = #summary]
+        #[doc = ""]
+        #[doc = #required]
+    }
+}
+
+/// The docs of `builder()` and the like.
+pub fn builder_fn(s: &Struct) -> TokenStream {
+    let doc = format!(" Returns a [`{}`] with no field set.", s.builder);
+    quote!(#[doc = #doc])
+}
+
+/// The docs of `build()`, or `call()` for a function.
+pub fn build_fn(s: &Struct) -> TokenStream {
+    let doc = match (&s.target, s.attrs.typestate) {
+        (Target::Fn(_), _) => format!(" Calls {} with the fields as arguments.", target(s)),
+        (_, true) => format!(" Builds the {}.", target(s)),
+        (_, false) => format!(
+            " Builds the {}, or returns an error naming what is missing.",
+            target(s),
+        ),
+    };
+    quote!(#[doc = #doc])
+}
+
+/// What the builder builds, as in ``[`Command`]``.
+fn target(s: &Struct) -> String {
+    match &s.target {
+        Target::Struct => format!("[`{}`]", s.ident),
+        Target::Variant(variant) => format!("[`{0}::{1}`]({0}::{1})", s.ident, variant),
+        Target::Fn(call) => format!("`{}`", call.ident),
+    }
+}
+
+/// `r#type` becomes `type`.
+fn unraw(ident: &Ident) -> String {
+    ident.to_string().trim_start_matches("r#").to_owned()
+}
@@ -0,0 +1,107 @@
+// `from_env()` for builders with `#[builder(env = "VAR")]` fields.
+//
+// Each such field is parsed from its variable with `FromStr`, and a variable
+// that is not present leaves the field unset. The lookup is a closure in
+// `from_env_with` so tests can supply their own environment.
+
+use crate::expand::Struct;
+use crate::{error, fallible};
+use proc_macro2::TokenStream;
+use quote::quote;
+
+pub fn expand(s: &Struct) -> TokenStream {
+    let (core, alloc) = (s.std.core(), s.std.alloc());
+    let fields: Vec<_> = s.fields.iter().filter(|f| f.attrs.env.is_some()).collect();
+    if fields.is_empty() {
+        return TokenStream::new();
+    }
+
+    let vis = s.vis;
+    let ident = s.ident;
+    let builder = &s.builder;
+    let error_ty = error::return_ty(s);
+    let (impl_generics, ty_generics, where_clause) = s.generics.split_for_impl();
+
+    // Bounds that only matter for generic field types.
+    let bounds = fields
+        .iter()
+        .filter(|_| !s.generics.params.is_empty())
+        .map(|f| {
+            let ty = f.setter_ty();
+            quote! {
+                #ty: #core::str::FromStr,
+                <#ty as #core::str::FromStr>::Err: #core::fmt::Display,
+            }
+        });
+
+    let init = s.fields.iter().map(|f| {
+        let name = &f.ident;
+        quote!(#name: #core::option::Option::None)
+    });
+    let invalid = fallible::init(s);
+    let (_, marker) = s.marker();
+
+    let reads = fields.iter().map(|f| {
+        let name = &f.ident;
+        let field = name.to_string();
+        let var = f.attrs.env.as_ref().unwrap();
+        let ty = f.setter_ty();
+        let bail = error::bail(
+            s,
+            quote! {
+                InvalidEnv {
+                    field: #field,
+                    var: #var,
+                    message: #alloc::string::ToString::to_string(&err),
+                }
+            },
+        );
+        quote! {
+            if let #core::option::Option::Some(value) = lookup(#var) {
+                match <#ty as #core::str::FromStr>::from_str(&value) {
+                    #core::result::Result::Ok(value) => {
+                        builder.#name = #core::option::Option::Some(value);
+                    }
+                    #core::result::Result::Err(err) => #bail,
+                }
+            }
+        }
+    });
+
+    let bounds: Vec<TokenStream> = bounds.collect();
+    let bounds2 = &bounds;
+    let doc = format!(
+        "Returns a builder with the fields of [`{}`] that have an `env` \
+         attribute read from the process environment.",
+        ident,
+    );
+
+    quote! {
+        impl #impl_generics #builder #ty_generics #where_clause {
+            #[doc = #doc]
+            #vis fn from_env() -> #core::result::Result<Self, #error_ty>
+            where
+                #(#bounds2)*
+            {
+                Self::from_env_with(|var| #core::env::var(var).ok())
+            }
+
+            /// Like `from_env`, but looks variables up with `lookup`.
+            #vis fn from_env_with<__F>(
+                mut lookup: __F,
+            ) -> #core::result::Result<Self, #error_ty>
+            where
+                __F: #core::ops::FnMut(&str) -> #core::option::Option<#alloc::string::String>,
+                #(#bounds2)*
+            {
+                let mut builder = #builder {
+                    #(#init,)*
+                    #invalid
+                    #marker
+                };
+                #(#reads)*
+                #core::result::Result::Ok(builder)
+            }
+        }
+    }
+}
@@ -0,0 +1,233 @@
+// The error type returned by `build()`.
+//
+// Every builder gets its own `{Name}BuilderError` enum next to it, since a
+// proc-macro crate cannot export a shared one. With `#[builder(error = "...")]`
+// `build()` returns the caller's type instead and converts into it with
+// `From`, so the generated enum is still emitted for that conversion.
+//
+// Besides `UninitializedField` and `ValidationError`, the enum only has the
+// variants that its builder can return. It is `#[non_exhaustive]`, so that
+// other crates matching on it are not broken when a change to the struct adds
+// one.
+
+use crate::attr::Rule;
+use crate::expand::{Kind, Struct};
+use crate::fallible;
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::Ident;
+
+pub fn ident(s: &Struct) -> Ident {
+    format_ident!("{}Error", s.builder)
+}
+
+/// The `E` in the `Result<T, E>` returned by `build()`.
+pub fn return_ty(s: &Struct) -> TokenStream {
+    match &s.attrs.error {
+        Some(path) => quote!(#path),
+        None => {
+            let ident = ident(s);
+            quote!(#ident)
+        }
+    }
+}
+
+/// An expression that returns `variant` from `build()`, converted into the
+/// return type.
+pub fn bail(s: &Struct, variant: TokenStream) -> TokenStream {
+    let core = s.std.core();
+    let ident = ident(s);
+    quote! {
+        return #core::result::Result::Err(#core::convert::From::from(#ident::#variant))
+    }
+}
+
+/// Statement that runs the `build_fn(validate = "...")` hook, if any, on
+/// `builder` and returns its message as a `ValidationError`.
+pub fn validate(s: &Struct, builder: TokenStream) -> TokenStream {
+    let core = s.std.core();
+    let path = match &s.attrs.validate {
+        Some(path) => path,
+        None => return TokenStream::new(),
+    };
+    let bail = bail(s, quote!(ValidationError(message)));
+    quote! {
+        if let #core::result::Result::Err(message) = #path(#builder) {
+            #bail;
+        }
+    }
+}
+
+pub fn expand(s: &Struct) -> TokenStream {
+    let (core, alloc) = (s.std.core(), s.std.alloc());
+    let vis = s.vis;
+    let ident = ident(s);
+    let doc = format!("Error returned by [`{}::{}`].", s.builder, s.build_fn());
+
+    // Variants that only some builders can produce are left out of the others,
+    // so that matching on the error does not need unreachable arms.
+    let mut variants = Vec::new();
+    let mut display = Vec::new();
+
+    if s.fields.iter().any(|f| f.attrs.env.is_some()) {
+        variants.push(quote! {
+            /// An environment variable could not be parsed into its field.
+            InvalidEnv {
+                field: &'static str,
+                var: &'static str,
+                message: #alloc::string::String,
+            },
+        });
+        display.push(quote! {
+            #ident::InvalidEnv { field, var, message } => #core::write!(
+                f,
+                "invalid value for `{}` in environment variable `{}`: {}",
+                field, var, message,
+            ),
+        });
+    }
+
+    // A single missing field of the struct's own is `UninitializedField`.
+    let required = s.fields.iter().filter(|f| f.is_required()).count();
+    if !s.attrs.typestate && (required > 1 || s.has_flattened()) {
+        variants.push(quote! {
+            /// More than one required field was never set, or one of a
+            /// flattened builder. Those of nested builders are named by
+            /// their path, as in `net.host`.
+            MissingFields(#alloc::vec::Vec<#alloc::string::String>),
+        });
+        display.push(quote! {
+            #ident::MissingFields(fields) => match fields.as_slice() {
+                [field] => #core::write!(f, "`{}` must be initialized", field),
+                _ => #core::write!(f, "missing fields: {}", fields.join(", ")),
+            },
+        });
+    }
+
+    // Typestate builders check groups at compile time.
+    let rules: Vec<Rule> = if s.attrs.typestate {
+        Vec::new()
+    } else {
+        s.attrs.groups.iter().map(|group| group.rule).collect()
+    };
+    if rules.contains(&Rule::ExactlyOne) {
+        variants.push(quote! {
+            /// None of the fields of an `exactly_one` group was set.
+            GroupNotSet {
+                group: &'static str,
+                fields: &'static [&'static str],
+            },
+            /// More than one field of an `exactly_one` group was set.
+            GroupConflict {
+                group: &'static str,
+                /// The fields that were set.
+                fields: #alloc::vec::Vec<&'static str>,
+            },
+        });
+        display.push(quote! {
+            #ident::GroupNotSet { group, fields } => #core::write!(
+                f,
+                "exactly one of {} must be set (group `{}`)",
+                fields.join(", "),
+                group,
+            ),
+            #ident::GroupConflict { group, fields } => #core::write!(
+                f,
+                "only one of {} may be set (group `{}`)",
+                fields.join(", "),
+                group,
+            ),
+        });
+    }
+    if rules.contains(&Rule::RequiresAll) {
+        variants.push(quote! {
+            /// Some but not all fields of a `requires_all` group were set.
+            GroupIncomplete {
+                group: &'static str,
+                /// The fields that were not set.
+                missing: #alloc::vec::Vec<&'static str>,
+            },
+        });
+        display.push(quote! {
+            #ident::GroupIncomplete { group, missing } => #core::write!(
+                f,
+                "{} must also be set (group `{}`)",
+                missing.join(", "),
+                group,
+            ),
+        });
+    }
+
+    if fallible::has_deferred(s) {
+        variants.push(quote! {
+            /// A `try_into = "deferred"` setter was given a value that did
+            /// not convert.
+            InvalidValue {
+                field: &'static str,
+                message: #alloc::string::String,
+            },
+        });
+        display.push(quote! {
+            #ident::InvalidValue { field, message } => {
+                #core::write!(f, "invalid value for `{}`: {}", field, message)
+            }
+        });
+    }
+
+    if s.fields.iter().any(|f| matches!(f.kind, Kind::Sub(..))) {
+        variants.push(quote! {
+            /// The nested builder of a `sub_builder` field failed to build.
+            SubBuilder {
+                field: &'static str,
+                /// The nested builder's unset required fields, including
+                /// those of the builders nested in it.
+                missing: #alloc::vec::Vec<#alloc::string::String>,
+                /// The nested builder's error.
+                message: #alloc::string::String,
+            },
+        });
+        display.push(quote! {
+            #ident::SubBuilder { field, missing, message } => match missing.as_slice() {
+                [] => #core::write!(f, "invalid `{}`: {}", field, message),
+                [nested] => #core::write!(f, "`{}.{}` must be initialized", field, nested),
+                _ => {
+                    f.write_str("missing fields: ")?;
+                    for (i, nested) in missing.iter().enumerate() {
+                        if i > 0 {
+                            f.write_str(", ")?;
+                        }
+                        #core::write!(f, "{}.{}", field, nested)?;
+                    }
+                    #core::result::Result::Ok(())
+                }
+            },
+        });
+    }
+
+    quote! {
+        #[doc = #doc]
+        #[derive(#core::fmt::Debug)]
+        #[non_exhaustive]
+        #vis enum #ident {
+            /// A required field was never set.
+            UninitializedField(&'static str),
+            /// The builder's values were rejected by its validation function.
+            ValidationError(#alloc::string::String),
+            #(#variants)*
+        }
+
+        impl #core::fmt::Display for #ident {
+            fn fmt(&self, f: &mut #core::fmt::Formatter) -> #core::fmt::Result {
+                match self {
+                    #ident::UninitializedField(field) => {
+                        #core::write!(f, "`{}` must be initialized", field)
+                    }
+                    #ident::ValidationError(message) => f.write_str(message),
+                    #(#display)*
+                }
+            }
+        }
+
+        impl #core::error::Error for #ident {}
+    }
+}
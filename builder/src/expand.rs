@@ -0,0 +1,1399 @@
+use crate::attr::{
+    self, DefaultValue, Errors, Fallible, FieldAttrs, Merge, Pattern, Rule, StructAttrs,
+};
+use crate::func::Call;
+use crate::no_std::Std;
+use crate::setter::{self, Setter};
+use crate::ty::{self, Item};
+use crate::{constant, doc, env, error, fallible, flatten, inspect, typestate};
+use proc_macro2::{Span, TokenStream};
+use quote::{format_ident, quote, ToTokens};
+use syn::spanned::Spanned;
+use syn::{
+    parse_quote, Attribute, Data, DeriveInput, Error, Fields, GenericParam, Generics, Ident, Index,
+    Member, Result, Token, Type, Visibility,
+};
+
+pub struct Struct<'a> {
+    pub ident: &'a Ident,
+    /// What the builder produces.
+    pub target: Target,
+    pub vis: &'a Visibility,
+    pub generics: &'a Generics,
+    pub builder: Ident,
+    pub attrs: StructAttrs,
+    pub fields: Vec<Field<'a>>,
+    /// The builder's own methods that a setter has the name of. The clash is
+    /// reported at the setter, and the method left out.
+    pub shadowed: Vec<&'static str>,
+    /// How the generated code names the standard library.
+    pub std: Std,
+}
+
+/// The methods of a runtime builder besides `build()`, which no setter may be
+/// named after.
+const METHODS: &[&str] = &["merge", "missing_fields", "reset"];
+
+pub struct Field<'a> {
+    /// Name of the field in the builder, of its setter, and of its local in
+    /// `build()`. Positional fields are `_0`, `_1`, ... unless they have
+    /// `#[builder(name = "...")]`.
+    pub ident: Ident,
+    /// How the field is accessed on the struct itself.
+    pub member: Member,
+    pub ty: &'a Type,
+    pub kind: Kind<'a>,
+    pub attrs: FieldAttrs,
+    /// Whether setters convert their argument with `Into`.
+    pub into: bool,
+    /// Whether the methods touching the field are `const fn` in `const`
+    /// mode, which they cannot be for an `each` field rejected there since
+    /// a const fn cannot drop its collection.
+    pub constant: bool,
+    pub std: Std,
+}
+
+pub enum Target {
+    /// `build()` returns the struct.
+    Struct,
+    /// `build()` returns this variant of the enum.
+    Variant(Ident),
+    /// `call()` calls a function with the fields as its arguments.
+    Fn(Box<Call>),
+}
+
+pub enum Kind<'a> {
+    /// Must be set before `build()`, unless it has a default.
+    Required,
+    /// Written as `Option<T>`; holds `T` and defaults to `None`.
+    Optional(&'a Type),
+    /// Has `#[builder(each = "...")]`; holds what the `each` setter adds.
+    Each(Item<'a>),
+    /// Has `#[builder(sub_builder)]`; holds the builder of the field's type.
+    Sub(Box<Type>, Nesting),
+}
+
+/// How a builder reaches the builder of a `sub_builder` or `flatten` field.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Nesting {
+    /// Through the `__{Struct}Nested` trait of the field's type.
+    Trait,
+    /// As the builder it is, which an owned builder holds when it flattens an
+    /// owned one.
+    Held,
+}
+
+// Both expansions bind every field to a local of the same name in declaration
+// order before constructing the struct, which is what lets a
+// `default = "..."` expression refer to the fields declared above it.
+
+pub fn derive(input: &DeriveInput) -> Result<TokenStream> {
+    // Mistakes in the attributes are reported all at once, next to a builder
+    // generated as if the offending attributes were not there, which keeps
+    // code using the builder from adding errors of its own.
+    let mut errors = Errors::default();
+
+    let builders = match &input.data {
+        Data::Struct(data) => {
+            let s = Struct::new(
+                input,
+                Target::Struct,
+                &data.fields,
+                &input.attrs,
+                &mut errors,
+            );
+            derive_struct(&s)
+        }
+        // Every variant gets its own builder, as if it were a struct with the
+        // variant's fields. Attributes on the enum apply to all of them.
+        Data::Enum(data) => data
+            .variants
+            .iter()
+            .map(|variant| {
+                let attrs = [&input.attrs[..], &variant.attrs[..]].concat();
+                let target = Target::Variant(variant.ident.clone());
+                let s = Struct::new(input, target, &variant.fields, &attrs, &mut errors);
+                derive_struct(&s)
+            })
+            .collect(),
+        Data::Union(_) => {
+            return Err(Error::new_spanned(
+                &input.ident,
+                "Builder can only be derived for structs and enums",
+            ))
+        }
+    };
+
+    let errors = errors.to_compile_error();
+    Ok(quote! {
+        #builders
+        #errors
+    })
+}
+
+pub fn derive_struct(s: &Struct) -> TokenStream {
+    // Before the builder's setters, which use them.
+    let mut tokens = flatten::checks(s);
+    tokens.extend(if s.attrs.typestate {
+        typestate::expand(s)
+    } else {
+        expand(s)
+    });
+    tokens.extend(flatten::expand(s));
+    tokens
+}
+
+impl<'a> Struct<'a> {
+    /// Collects the struct's fields and options. Invalid options are added
+    /// to `errors` and otherwise ignored.
+    pub fn new(
+        input: &'a DeriveInput,
+        target: Target,
+        fields: &'a Fields,
+        attrs: &[Attribute],
+        errors: &mut Errors,
+    ) -> Self {
+        let fields: Vec<&syn::Field> = match fields {
+            Fields::Named(fields) => fields.named.iter().collect(),
+            Fields::Unnamed(fields) => fields.unnamed.iter().collect(),
+            Fields::Unit => Vec::new(),
+        };
+
+        let mut struct_attrs = attr::struct_attrs(attrs, errors);
+
+        if let (true, Some(path), None) = (
+            struct_attrs.typestate,
+            &struct_attrs.error,
+            &struct_attrs.validate,
+        ) {
+            errors.push(Error::new_spanned(
+                path,
+                "`error` has no effect in typestate mode without `build_fn(validate)` \
+                 because build() cannot fail",
+            ));
+            struct_attrs.error = None;
+        }
+
+        if struct_attrs.typestate && struct_attrs.serde {
+            errors.push(Error::new_spanned(
+                input,
+                "`serde` is not available in typestate mode",
+            ));
+            struct_attrs.serde = false;
+        }
+
+        if let (true, Some(derive)) = (struct_attrs.typestate, struct_attrs.derives.first()) {
+            errors.push(Error::new_spanned(
+                derive,
+                "`derive` is not available in typestate mode",
+            ));
+            struct_attrs.derives.clear();
+        }
+
+        if let Some(flattenable) = &struct_attrs.flattenable {
+            let error = match target {
+                Target::Struct if struct_attrs.typestate => {
+                    Some("`flattenable` is not available in typestate mode")
+                }
+                Target::Struct if !input.generics.params.is_empty() => {
+                    Some("`flattenable` is not available for a generic struct")
+                }
+                Target::Struct => None,
+                Target::Variant(_) | Target::Fn(_) => {
+                    Some("`flattenable` is only available on structs")
+                }
+            };
+            if let Some(error) = error {
+                errors.push(Error::new_spanned(flattenable, error));
+                struct_attrs.flattenable = None;
+            }
+        }
+
+        if struct_attrs.typestate && struct_attrs.constant {
+            errors.push(Error::new_spanned(
+                input,
+                "`const` is not available in typestate mode",
+            ));
+            struct_attrs.constant = false;
+        }
+
+        if struct_attrs.constant {
+            if let Some(validate) = struct_attrs.validate.take() {
+                errors.push(Error::new_spanned(
+                    validate,
+                    "`build_fn(validate)` is not available with `const` \
+                     because build_const() cannot call it",
+                ));
+            }
+            if struct_attrs.into {
+                errors.push(Error::new_spanned(
+                    input,
+                    "`setter(into)` is not available with `const` \
+                     because `Into` cannot be called in const fn",
+                ));
+                struct_attrs.into = false;
+            }
+        }
+
+        let fields: Vec<Field> = fields
+            .into_iter()
+            .enumerate()
+            .map(|(i, field)| Field::new(i, field, &struct_attrs, errors))
+            .collect();
+
+        let typestate = struct_attrs.typestate;
+        struct_attrs.groups.retain(|group| {
+            for (i, member) in group.fields.iter().enumerate() {
+                let error = if group.fields[..i].contains(member) {
+                    Error::new_spanned(member, "duplicate field in group")
+                } else {
+                    match fields.iter().find(|f| f.ident == *member) {
+                        None => Error::new_spanned(member, "no field with this name"),
+                        Some(f) if group.rule == Rule::ExactlyOne && f.is_required() => {
+                            Error::new_spanned(
+                                member,
+                                "fields of an `exactly_one` group must be optional",
+                            )
+                        }
+                        Some(f) if typestate && !matches!(f.kind, Kind::Optional(_)) => {
+                            Error::new_spanned(
+                                member,
+                                "in typestate mode, fields of a group must be of type Option<T>",
+                            )
+                        }
+                        Some(_) => continue,
+                    }
+                };
+                errors.push(error);
+                return false;
+            }
+            true
+        });
+
+        let mut shadowed = Vec::new();
+        if !typestate {
+            let setters = fields
+                .iter()
+                .filter(|f| !f.attrs.flatten)
+                .flat_map(setter::setters);
+            for setter in setters {
+                if let Some(method) = METHODS.iter().find(|method| setter.name == method) {
+                    errors.push(Error::new_spanned(
+                        &setter.name,
+                        format!(
+                            "setter `{0}` clashes with the builder's own `{0}()` method",
+                            method,
+                        ),
+                    ));
+                    shadowed.push(*method);
+                }
+            }
+        }
+
+        let builder = match &target {
+            Target::Variant(variant) => format_ident!("{}{}Builder", input.ident, variant),
+            Target::Struct | Target::Fn(_) => format_ident!("{}Builder", input.ident),
+        };
+
+        Struct {
+            ident: &input.ident,
+            target,
+            vis: &input.vis,
+            generics: &input.generics,
+            builder,
+            std: Std::new(struct_attrs.no_std),
+            attrs: struct_attrs,
+            fields,
+            shadowed,
+        }
+    }
+}
+
+impl<'a> Field<'a> {
+    fn new(
+        i: usize,
+        field: &'a syn::Field,
+        struct_attrs: &StructAttrs,
+        errors: &mut Errors,
+    ) -> Self {
+        let mut attrs = attr::field_attrs(&field.attrs, errors);
+        let (ident, member) = match &field.ident {
+            Some(ident) => (
+                attrs.name.clone().unwrap_or_else(|| ident.clone()),
+                Member::Named(ident.clone()),
+            ),
+            None => (
+                attrs
+                    .name
+                    .clone()
+                    .unwrap_or_else(|| format_ident!("_{}", i)),
+                Member::Unnamed(Index::from(i)),
+            ),
+        };
+        if attrs.strip_option.is_some() && ty::option_inner(&field.ty).is_none() {
+            errors.push(Error::new_spanned(
+                &field.ty,
+                "`strip_option` only applies to fields of type Option<T>",
+            ));
+            attrs.strip_option = None;
+        }
+        if let (false, Some(rename)) = (struct_attrs.serde, &attrs.rename) {
+            errors.push(Error::new_spanned(
+                rename,
+                "`rename` applies to the serialized builder, which needs `builder(serde)`",
+            ));
+            attrs.rename = None;
+        }
+        if let (true, Some(env)) = (struct_attrs.no_std, &attrs.env) {
+            errors.push(Error::new_spanned(
+                env,
+                "`env` needs the standard library and is not available with `no_std`",
+            ));
+            attrs.env = None;
+        }
+        if let (true, Some(env)) = (struct_attrs.typestate, &attrs.env) {
+            errors.push(Error::new_spanned(
+                env,
+                "`env` is not available in typestate mode",
+            ));
+            attrs.env = None;
+        }
+        if let (Some(env), Some(_)) = (&attrs.env, &attrs.each) {
+            errors.push(Error::new_spanned(
+                env,
+                "`env` cannot be combined with `each`",
+            ));
+            attrs.env = None;
+        }
+        if let (Some(Merge::Append), None) = (attrs.merge, &attrs.each) {
+            errors.push(Error::new_spanned(
+                field,
+                "`merge = \"append\"` requires `each = \"...\"`",
+            ));
+            attrs.merge = None;
+        }
+        if let (true, Some(_)) = (struct_attrs.typestate, attrs.merge) {
+            errors.push(Error::new_spanned(
+                field,
+                "`merge` is not available in typestate mode",
+            ));
+            attrs.merge = None;
+        }
+        // A flattened field is built through its builder like a
+        // `sub_builder` one, with the builder's setters on this builder.
+        attrs.sub_builder |= attrs.flatten;
+        let sub_builder = if attrs.flatten {
+            "flatten"
+        } else {
+            "sub_builder"
+        };
+        let mut constant = struct_attrs.constant;
+        if struct_attrs.constant {
+            if let Some(each) = attrs.each.take() {
+                errors.push(Error::new_spanned(
+                    each,
+                    "`each` fields need allocation and are not available with `const`",
+                ));
+                attrs.merge = None;
+                constant = false;
+            }
+            if attrs.sub_builder {
+                errors.push(Error::new_spanned(
+                    field,
+                    format!("`{}` is not available with `const`", sub_builder),
+                ));
+                attrs.sub_builder = false;
+            }
+            if let Some(true) = attrs.into {
+                errors.push(Error::new_spanned(
+                    field,
+                    "`setter(into)` is not available with `const` \
+                     because `Into` cannot be called in const fn",
+                ));
+                attrs.into = None;
+            }
+            if attrs.try_into.take().is_some() {
+                errors.push(Error::new_spanned(
+                    field,
+                    "`setter(try_into)` is not available with `const` \
+                     because `TryInto` cannot be called in const fn",
+                ));
+            }
+            if let Some(DefaultValue::Trait) = attrs.default {
+                errors.push(Error::new_spanned(
+                    field,
+                    "`default` is not available with `const` because `Default` \
+                     cannot be called in const fn; use `default = \"...\"` \
+                     with a constant expression",
+                ));
+                attrs.default = None;
+            }
+        }
+        if let (true, Some(Fallible::Deferred)) = (struct_attrs.typestate, attrs.try_into) {
+            errors.push(Error::new_spanned(
+                field,
+                "`try_into = \"deferred\"` is not available in typestate mode, \
+                 whose `build()` cannot fail; use `try_into`",
+            ));
+            attrs.try_into = None;
+        }
+        if attrs.sub_builder {
+            let error = if struct_attrs.typestate {
+                Some(format!(
+                    "`{}` is not available in typestate mode",
+                    sub_builder,
+                ))
+            } else if attrs.each.is_some() || attrs.env.is_some() {
+                Some(format!(
+                    "`{}` cannot be combined with `each` or `env`",
+                    sub_builder,
+                ))
+            } else if attrs.try_into.is_some() {
+                Some(format!(
+                    "`{}` fields are set through their builder and cannot have \
+                     `setter(try_into)`",
+                    sub_builder,
+                ))
+            } else {
+                None
+            };
+            if let Some(error) = error {
+                errors.push(Error::new_spanned(field, error));
+                attrs.sub_builder = false;
+            }
+        }
+        let mut into = attrs.into.unwrap_or(struct_attrs.into);
+        let sub_builder = if attrs.sub_builder {
+            let builder = ty::builder_of(&field.ty);
+            if builder.is_none() {
+                errors.push(Error::new_spanned(
+                    &field.ty,
+                    format!("`{}` needs a field whose type derives Builder", sub_builder),
+                ));
+                attrs.sub_builder = false;
+            }
+            builder
+        } else {
+            None
+        };
+        attrs.flatten &= attrs.sub_builder;
+        // The macro of a builder in another crate is private to it. Such a
+        // field is nested as a `sub_builder` instead, and reported here when
+        // its path starts with `::`, which always names another crate.
+        if let (true, Type::Path(ty)) = (attrs.flatten, &field.ty) {
+            if ty.path.leading_colon.is_some() {
+                errors.push(Error::new_spanned(
+                    &field.ty,
+                    "`flatten` needs a struct defined in this crate; \
+                     use `sub_builder` for one from another crate",
+                ));
+                attrs.flatten = false;
+            }
+        }
+        let kind = if let Some(builder) = sub_builder {
+            // An owned builder can only be flattened into another owned one,
+            // which takes it by value in its setters and is not Clone.
+            if attrs.flatten && struct_attrs.pattern == Pattern::Owned {
+                Kind::Sub(Box::new(builder), Nesting::Held)
+            } else {
+                let builder = flatten::nested(&field.ty, "Builder");
+                Kind::Sub(Box::new(parse_quote!(#builder)), Nesting::Trait)
+            }
+        } else if let Some(each) = &attrs.each {
+            let item = ty::collection_item(&field.ty);
+            if let (Item::Inferred, true) = (&item, into) {
+                errors.push(Error::new_spanned(
+                    each,
+                    "setter(into) on an `each` field needs a std collection type \
+                     so that the element type is known",
+                ));
+                into = false;
+            }
+            Kind::Each(item)
+        } else if let Some(inner) = ty::option_inner(&field.ty) {
+            if attrs.strip_option == Some(false) {
+                // An unstripped `Option<T>` is a plain field whose setter
+                // takes the whole `Option` and which defaults to `None`.
+                let core = Std::new(struct_attrs.no_std).core();
+                attrs.default.get_or_insert_with(|| {
+                    DefaultValue::Expr(Box::new(parse_quote!(#core::option::Option::None)))
+                });
+                Kind::Required
+            } else {
+                Kind::Optional(inner)
+            }
+        } else {
+            Kind::Required
+        };
+        let no_setter = if attrs.flatten {
+            Some("a flattened field has no setter of its own")
+        } else if attrs.each.as_ref() == Some(&ident) {
+            Some("the field's only setter is its `each` setter")
+        } else {
+            None
+        };
+        if let (Some(reason), Some(alias)) = (no_setter, attrs.aliases.first()) {
+            errors.push(Error::new_spanned(
+                alias,
+                format!("`alias` names a former setter, but {}", reason),
+            ));
+            attrs.aliases.clear();
+        }
+        if attrs.boxed == Some(true) {
+            let ty = match kind {
+                Kind::Each(Item::Elem(elem)) => elem,
+                Kind::Optional(inner) => inner,
+                _ => &field.ty,
+            };
+            if ty::trait_object(ty).is_none() {
+                errors.push(Error::new_spanned(
+                    ty,
+                    "`setter(boxed)` needs a field of type Box<dyn Trait>, \
+                     Arc<dyn Trait> or Rc<dyn Trait>",
+                ));
+                attrs.boxed = None;
+            }
+        }
+        Field {
+            ident,
+            member,
+            ty: &field.ty,
+            kind,
+            into,
+            attrs,
+            constant,
+            std: Std::new(struct_attrs.no_std),
+        }
+    }
+}
+
+impl Struct<'_> {
+    /// The generic arguments matching the struct's generic parameters, as in
+    /// `'a, T, N` for `<'a, T: Clone, const N: usize>`.
+    pub fn generic_args(&self) -> Vec<TokenStream> {
+        self.generics
+            .params
+            .iter()
+            .map(|param| match param {
+                GenericParam::Type(param) => param.ident.to_token_stream(),
+                GenericParam::Lifetime(param) => param.lifetime.to_token_stream(),
+                GenericParam::Const(param) => param.ident.to_token_stream(),
+            })
+            .collect()
+    }
+
+    /// Whether the builder has its own method `name`, which it does not if a
+    /// setter has that name.
+    pub fn has_method(&self, name: &str) -> bool {
+        !self.shadowed.contains(&name)
+    }
+
+    /// `PhantomData` of the struct's type and lifetime parameters, for a
+    /// builder whose fields may leave some of them unused.
+    pub fn phantom(&self) -> TokenStream {
+        let core = self.std.core();
+        let params = self.generics.params.iter().filter_map(|param| match param {
+            GenericParam::Type(param) => Some(param.ident.to_token_stream()),
+            GenericParam::Lifetime(param) => {
+                let lifetime = &param.lifetime;
+                Some(quote!(&#lifetime ()))
+            }
+            GenericParam::Const(_) => None,
+        });
+        quote!(#core::marker::PhantomData<fn() -> (#(#params,)*)>)
+    }
+
+    /// The `__marker` field and its initializer, for the builder of a variant
+    /// of a generic enum, whose fields need not use all of the enum's
+    /// parameters.
+    pub fn marker(&self) -> (TokenStream, TokenStream) {
+        let core = self.std.core();
+        match self.target {
+            Target::Variant(_) if !self.generics.params.is_empty() => {
+                let phantom = self.phantom();
+                let serde = if self.attrs.serde {
+                    Some(quote!(#[serde(skip)]))
+                } else {
+                    None
+                };
+                (
+                    quote!(#serde __marker: #phantom,),
+                    quote!(__marker: #core::marker::PhantomData,),
+                )
+            }
+            _ => (TokenStream::new(), TokenStream::new()),
+        }
+    }
+
+    /// `T: Clone` bounds for every stored field type, needed by methods that
+    /// clone the builder's fields when the struct is generic. Non-generic
+    /// structs get none so that a missing impl is reported on the field.
+    pub fn clone_bounds(&self) -> Vec<TokenStream> {
+        let core = self.std.core();
+        if self.generics.params.is_empty() {
+            return Vec::new();
+        }
+        self.fields
+            .iter()
+            .map(|f| {
+                let ty = f.setter_ty();
+                quote!(#ty: #core::clone::Clone)
+            })
+            .collect()
+    }
+
+    /// The function returning a new builder: `builder()` for a struct,
+    /// `get_builder()` for an enum's `Get` variant and `connect_builder()` for
+    /// a function `connect`.
+    pub fn builder_fn(&self) -> Ident {
+        match &self.target {
+            Target::Struct => format_ident!("builder"),
+            Target::Variant(variant) => format_ident!("{}_builder", snake_case(variant)),
+            Target::Fn(call) => format_ident!("{}_builder", call.ident),
+        }
+    }
+
+    /// The builder's method that finishes it: `build()`, or `call()` for a
+    /// function.
+    pub fn build_fn(&self) -> Ident {
+        match &self.target {
+            Target::Struct | Target::Variant(_) => format_ident!("build"),
+            Target::Fn(_) => format_ident!("call"),
+        }
+    }
+
+    /// `const` for the functions that are `const fn` in `const` mode, as
+    /// long as every field can be used in one.
+    pub fn constness(&self) -> Option<Token![const]> {
+        if self.attrs.constant && self.fields.iter().all(|f| f.constant) {
+            Some(<Token![const]>::default())
+        } else {
+            None
+        }
+    }
+
+    /// `const` for the setters of `f` in `const` mode.
+    pub fn field_constness(&self, f: &Field) -> Option<Token![const]> {
+        if self.attrs.constant && f.constant {
+            Some(<Token![const]>::default())
+        } else {
+            None
+        }
+    }
+
+    /// Whether any field has `#[builder(flatten)]`, whose required fields
+    /// are only known once the builder runs.
+    pub fn has_flattened(&self) -> bool {
+        self.fields.iter().any(|f| f.attrs.flatten)
+    }
+
+    /// The unset required fields of the builder `this`, including those of
+    /// nested builders if `nested`, which are all strings.
+    pub fn missing(&self, this: &TokenStream, nested: fn(&Field) -> bool) -> TokenStream {
+        let alloc = self.std.alloc();
+        let checks = self.fields.iter().map(|f| {
+            let name = &f.ident;
+            let field = name.to_string();
+            if nested(f) {
+                f.nested_missing(this)
+            } else if f.is_required() {
+                quote! {
+                    if #this.#name.is_none() {
+                        missing.push(#alloc::string::String::from(#field));
+                    }
+                }
+            } else {
+                TokenStream::new()
+            }
+        });
+        quote! {
+            let mut missing = #alloc::vec::Vec::<#alloc::string::String>::new();
+            #(#checks)*
+            missing
+        }
+    }
+
+    /// Whether the field belongs to a `group(...)`.
+    pub fn in_group(&self, f: &Field) -> bool {
+        self.attrs
+            .groups
+            .iter()
+            .any(|group| group.fields.contains(&f.ident))
+    }
+
+    /// The struct expression `Name { member: local, ... }`, which works for
+    /// named, tuple and unit structs alike, and for enum variants as
+    /// `Name::Variant { ... }`. For a function, the call with every field as
+    /// an argument.
+    pub fn construct(&self) -> TokenStream {
+        let ident = self.ident;
+        let path = match &self.target {
+            Target::Struct => quote!(#ident),
+            Target::Variant(variant) => quote!(#ident::#variant),
+            Target::Fn(call) => return call.invoke(&self.fields),
+        };
+        let fields = self.fields.iter().map(|f| {
+            let member = &f.member;
+            let name = &f.ident;
+            quote!(#member: #name)
+        });
+        quote! {
+            #path {
+                #(#fields,)*
+            }
+        }
+    }
+}
+
+/// `tokens`, spanned at `span` so that errors in them point there.
+pub fn respan(tokens: TokenStream, span: Span) -> TokenStream {
+    tokens
+        .into_iter()
+        .map(|mut token| {
+            token.set_span(span);
+            token
+        })
+        .collect()
+}
+
+/// `send_request` becomes `SendRequest`.
+pub fn camel_case(ident: &Ident) -> String {
+    let mut camel = String::new();
+    for word in ident.to_string().trim_start_matches("r#").split('_') {
+        let mut chars = word.chars();
+        if let Some(first) = chars.next() {
+            camel.extend(first.to_uppercase());
+            camel.push_str(chars.as_str());
+        }
+    }
+    camel
+}
+
+/// `HttpGet` becomes `http_get`.
+fn snake_case(ident: &Ident) -> String {
+    let mut snake = String::new();
+    for (i, ch) in ident.to_string().trim_start_matches("r#").char_indices() {
+        if ch.is_uppercase() {
+            if i > 0 {
+                snake.push('_');
+            }
+            snake.extend(ch.to_lowercase());
+        } else {
+            snake.push(ch);
+        }
+    }
+    snake
+}
+
+impl<'a> Field<'a> {
+    /// The type held by the builder, which the all-at-once setter takes.
+    pub fn setter_ty(&self) -> &Type {
+        match &self.kind {
+            Kind::Optional(inner) => inner,
+            Kind::Sub(builder, _) => builder,
+            Kind::Required | Kind::Each(_) => self.ty,
+        }
+    }
+
+    /// Whether `build()` fails when this field was never set.
+    pub fn is_required(&self) -> bool {
+        matches!(self.kind, Kind::Required) && self.attrs.default.is_none()
+    }
+
+    /// The `#[builder(default)]` value of the field, if it has one.
+    pub fn default_value(&self) -> Option<TokenStream> {
+        let core = self.std.core();
+        match &self.attrs.default {
+            Some(DefaultValue::Trait) => Some(quote!(#core::default::Default::default())),
+            Some(DefaultValue::Expr(expr)) => Some(quote!(#expr)),
+            None => None,
+        }
+    }
+
+    /// The final value of the field, given `stored` of type
+    /// `Option<setter_ty>`. Required fields must already have been checked.
+    /// Sub-builder fields are built by `build()` itself instead.
+    pub fn value(&self, stored: TokenStream) -> TokenStream {
+        let core = self.std.core();
+        let unset = match self.default_value() {
+            Some(default) => default,
+            None => match self.kind {
+                Kind::Required | Kind::Sub(..) => quote!(#core::unreachable!()),
+                Kind::Optional(_) => quote!(#core::option::Option::None),
+                Kind::Each(_) => quote!(#core::default::Default::default()),
+            },
+        };
+        let set = match self.kind {
+            Kind::Optional(_) => quote!(#core::option::Option::Some(value)),
+            Kind::Required | Kind::Each(_) | Kind::Sub(..) => quote!(value),
+        };
+        quote! {
+            match #stored {
+                #core::option::Option::Some(value) => #set,
+                #core::option::Option::None => #unset,
+            }
+        }
+    }
+
+    /// The builder's storage for a field that is set to `value`.
+    pub fn stored(&self, value: TokenStream) -> TokenStream {
+        let core = self.std.core();
+        match self.kind {
+            Kind::Optional(_) => value,
+            Kind::Required | Kind::Each(_) => quote!(#core::option::Option::Some(#value)),
+            Kind::Sub(..) => {
+                let from = self.nested_call(
+                    "__from",
+                    value.clone(),
+                    quote!(#core::convert::From::from(#value)),
+                );
+                quote!(#core::option::Option::Some(#from))
+            }
+        }
+    }
+
+    /// The call of the function `item` of the `__{Struct}Nested` trait of a
+    /// `sub_builder` or `flatten` field's type with `args`, or `held` for a
+    /// nested builder that is held as is.
+    pub fn nested_call(&self, item: &str, args: TokenStream, held: TokenStream) -> TokenStream {
+        match self.kind {
+            Kind::Sub(_, Nesting::Held) => held,
+            _ => {
+                let function = flatten::nested(self.ty, item);
+                quote!(#function(#args))
+            }
+        }
+    }
+
+    /// A new builder for a `sub_builder` or `flatten` field.
+    pub fn new_nested(&self) -> TokenStream {
+        let ty = self.ty;
+        self.nested_call("__builder", TokenStream::new(), quote!(<#ty>::builder()))
+    }
+
+    /// Statements adding the required fields that the nested builder of a
+    /// `sub_builder` or `flatten` field of the builder `this` has not set to
+    /// `missing`, named by their path from this builder. A nested builder
+    /// that was never set is missing all of them, unless the field has a
+    /// default.
+    pub fn nested_missing(&self, this: &TokenStream) -> TokenStream {
+        let (core, alloc) = (self.std.core(), self.std.alloc());
+        let name = &self.ident;
+        let field = name.to_string();
+        let ty = self.ty;
+        let unset = match self.attrs.default {
+            Some(_) => quote!(#alloc::vec::Vec::new()),
+            None => {
+                let new = self.new_nested();
+                let held = quote!(<#ty>::builder().missing_fields());
+                self.nested_call("__missing", quote!(&#new), held)
+            }
+        };
+        let set = self.nested_call(
+            "__missing",
+            quote!(builder),
+            quote!(builder.missing_fields()),
+        );
+        quote! {
+            let nested = match &#this.#name {
+                #core::option::Option::Some(builder) => #set,
+                #core::option::Option::None => #unset,
+            };
+            for nested in nested {
+                missing.push(#alloc::format!("{}.{}", #field, nested));
+            }
+        }
+    }
+
+    /// The field's storage in the builder `b`, to assign to. It is spanned at
+    /// the field's type, where a const fn that cannot drop the value it
+    /// replaces reports that.
+    pub fn place(&self, b: &TokenStream) -> TokenStream {
+        let name = &self.ident;
+        respan(quote!(#b.#name), self.ty.span())
+    }
+
+    /// Whether the all-at-once setter is generated. It is skipped when the
+    /// `each` setter has the same name as the field.
+    pub fn has_setter(&self) -> bool {
+        match &self.attrs.each {
+            Some(each) => *each != self.ident,
+            None => true,
+        }
+    }
+}
+
+fn expand(s: &Struct) -> TokenStream {
+    let (core, alloc) = (s.std.core(), s.std.alloc());
+    let Struct {
+        ident,
+        vis,
+        generics,
+        builder,
+        ..
+    } = s;
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let storage = s.fields.iter().map(|f| {
+        let name = &f.ident;
+        let ty = f.setter_ty();
+        let serde = if s.attrs.serde {
+            let rename = f
+                .attrs
+                .rename
+                .as_ref()
+                .map(|rename| quote!(rename = #rename,));
+            let is_none = if s.attrs.no_std {
+                "::core::option::Option::is_none"
+            } else {
+                "::std::option::Option::is_none"
+            };
+            Some(quote! {
+                #[serde(#rename default, skip_serializing_if = #is_none)]
+            })
+        } else {
+            None
+        };
+        quote! {
+            #serde
+            #name: #core::option::Option<#ty>
+        }
+    });
+
+    let init = s.fields.iter().map(|f| {
+        let name = &f.ident;
+        quote!(#name: #core::option::Option::None)
+    });
+
+    let setters = s.fields.iter().map(|f| setter::field_setters(s, f));
+
+    // Not a const fn even in `const` mode, since it drops what it does not
+    // take from `other`.
+    let merge = if s.has_method("merge") {
+        let merge = Setter::new(
+            &format_ident!("merge"),
+            TokenStream::new(),
+            quote!(other: Self),
+            Vec::new(),
+            |b| {
+                let fields = s.fields.iter().map(|f| {
+                    let name = &f.ident;
+                    match f.attrs.merge {
+                        Some(Merge::Append) => quote! {
+                            if let #core::option::Option::Some(items) = other.#name {
+                                #core::iter::Extend::extend(
+                                    #b.#name.get_or_insert_with(#core::default::Default::default),
+                                    items,
+                                );
+                            }
+                        },
+                        // Both nested builders are merged in turn, since the
+                        // setters of a flattened one look like this builder's.
+                        Some(Merge::Replace) | None if matches!(f.kind, Kind::Sub(..)) => {
+                            let place = f.place(&b);
+                            let (builder, nested) =
+                                (format_ident!("builder"), format_ident!("nested"));
+                            let merged = f.nested_call(
+                                "__merge",
+                                quote!(#builder, #nested),
+                                flatten::merge_held(f, &builder, &nested),
+                            );
+                            quote! {
+                                if let #core::option::Option::Some(#nested) = other.#name {
+                                    #place = #core::option::Option::Some(match #b.#name.take() {
+                                        #core::option::Option::Some(#builder) => #merged,
+                                        #core::option::Option::None => #nested,
+                                    });
+                                }
+                            }
+                        }
+                        Some(Merge::Replace) | None => {
+                            let place = f.place(&b);
+                            quote! {
+                                if other.#name.is_some() {
+                                    #place = other.#name;
+                                }
+                            }
+                        }
+                    }
+                });
+                let invalid = fallible::merge(s, &b);
+                quote!({ #invalid #(#fields)* })
+            },
+        );
+        let doc = "Takes the fields that are set in `other`, adding to the collections of \
+                   `merge = \"append\"` fields and merging nested builders.";
+        s.method(None, merge.with_attrs(quote!(#[doc = #doc])))
+    } else {
+        TokenStream::new()
+    };
+
+    let required: Vec<&Field> = s.fields.iter().filter(|f| f.is_required()).collect();
+
+    let missing_fields = if s.has_method("missing_fields") {
+        let missing = s.missing(&quote!(self), |f| matches!(f.kind, Kind::Sub(..)));
+        quote! {
+            /// The names of the required fields that have not been set, in
+            /// the order they are declared. Those of nested builders are named
+            /// by their path, as in `config.retry.attempts`.
+            #vis fn missing_fields(&self) -> #alloc::vec::Vec<#alloc::string::String> {
+                #missing
+            }
+        }
+    } else {
+        TokenStream::new()
+    };
+
+    // A single missing field of this struct is reported as
+    // `UninitializedField`, and anything else as `MissingFields`. Fields of
+    // flattened builders are checked here, while those of sub-builders are
+    // reported by the nested build.
+    let checks = match required.as_slice() {
+        [] if !s.has_flattened() => None,
+        [f] if !s.has_flattened() => {
+            let name = &f.ident;
+            let field = name.to_string();
+            let bail = error::bail(s, quote!(UninitializedField(#field)));
+            Some(quote! {
+                if self.#name.is_none() {
+                    #bail;
+                }
+            })
+        }
+        _ => {
+            let missing = s.missing(&quote!(self), |f| f.attrs.flatten);
+            let uninitialized = required.iter().map(|f| {
+                let name = &f.ident;
+                let field = name.to_string();
+                let bail = error::bail(s, quote!(UninitializedField(#field)));
+                quote! {
+                    if self.#name.is_none() {
+                        #bail;
+                    }
+                }
+            });
+            let missing_fields = error::bail(s, quote!(MissingFields(missing)));
+            Some(quote! {
+                let missing = { #missing };
+                if missing.len() == 1 {
+                    #(#uninitialized)*
+                }
+                if !missing.is_empty() {
+                    #missing_fields;
+                }
+            })
+        }
+    };
+
+    let group_checks = s.attrs.groups.iter().map(|group| {
+        let name = group.name.value();
+        let set = group.fields.iter().map(|member| {
+            let field = member.to_string();
+            quote! {
+                if self.#member.is_some() {
+                    set.push(#field);
+                }
+            }
+        });
+        let check = match group.rule {
+            Rule::ExactlyOne => {
+                let fields = group.fields.iter().map(|member| member.to_string());
+                let not_set = error::bail(
+                    s,
+                    quote!(GroupNotSet {
+                        group: #name,
+                        fields: &[#(#fields),*],
+                    }),
+                );
+                let conflict = error::bail(
+                    s,
+                    quote!(GroupConflict {
+                        group: #name,
+                        fields: set,
+                    }),
+                );
+                quote! {
+                    if set.is_empty() {
+                        #not_set;
+                    } else if set.len() > 1 {
+                        #conflict;
+                    }
+                }
+            }
+            Rule::RequiresAll => {
+                let missing = group.fields.iter().map(|member| {
+                    let field = member.to_string();
+                    quote! {
+                        if self.#member.is_none() {
+                            missing.push(#field);
+                        }
+                    }
+                });
+                let incomplete = error::bail(
+                    s,
+                    quote!(GroupIncomplete {
+                        group: #name,
+                        missing,
+                    }),
+                );
+                quote! {
+                    if !set.is_empty() {
+                        let mut missing = #alloc::vec::Vec::new();
+                        #(#missing)*
+                        if !missing.is_empty() {
+                            #incomplete;
+                        }
+                    }
+                }
+            }
+        };
+        quote! {{
+            let mut set = #alloc::vec::Vec::new();
+            #(#set)*
+            #check
+        }}
+    });
+
+    let (receiver, validate, build_bounds) = match s.attrs.pattern {
+        Pattern::Owned => (quote!(self), error::validate(s, quote!(&self)), Vec::new()),
+        Pattern::Mutable | Pattern::Immutable => (
+            quote!(&self),
+            error::validate(s, quote!(self)),
+            s.clone_bounds(),
+        ),
+    };
+
+    let bindings = s.fields.iter().map(|f| {
+        let name = &f.ident;
+        let stored = match s.attrs.pattern {
+            Pattern::Owned => quote!(self.#name),
+            Pattern::Mutable | Pattern::Immutable => {
+                quote!(#core::clone::Clone::clone(&self.#name))
+            }
+        };
+        match &f.kind {
+            // The nested builder's missing fields are read before building it
+            // so that the error can name them.
+            Kind::Sub(..) => {
+                let field = name.to_string();
+                let bail = error::bail(
+                    s,
+                    quote!(SubBuilder {
+                        field: #field,
+                        missing,
+                        message: #alloc::string::ToString::to_string(&error),
+                    }),
+                );
+                let missing = f.nested_call(
+                    "__missing",
+                    quote!(&builder),
+                    quote!(builder.missing_fields()),
+                );
+                let build = f.nested_call("__build", quote!(builder), quote!(builder.build()));
+                let build = quote! {{
+                    let missing = #missing;
+                    match #build {
+                        #core::result::Result::Ok(value) => value,
+                        #core::result::Result::Err(error) => #bail,
+                    }
+                }};
+                let unset = match f.default_value() {
+                    Some(default) => default,
+                    None => {
+                        let new = f.new_nested();
+                        quote!({
+                            let builder = #new;
+                            #build
+                        })
+                    }
+                };
+                quote! {
+                    let #name = match #stored {
+                        #core::option::Option::Some(builder) => #build,
+                        #core::option::Option::None => #unset,
+                    };
+                }
+            }
+            _ => {
+                let value = f.value(stored);
+                quote!(let #name = #value;)
+            }
+        }
+    });
+
+    let construct = s.construct();
+    let error_ty = error::return_ty(s);
+    let error = error::expand(s);
+
+    // Builders that clone in build() already require every field to be
+    // Clone, so only those derive it.
+    let derive_clone = match s.attrs.pattern {
+        Pattern::Owned => None,
+        Pattern::Mutable | Pattern::Immutable => Some(quote!(#[derive(#core::clone::Clone)])),
+    };
+    let conversions = match s.target {
+        Target::Struct => Some(conversions(s, &build_bounds)),
+        // A value of the enum may be any variant, so there is nothing to
+        // convert from.
+        Target::Variant(_) | Target::Fn(_) => None,
+    };
+
+    let from_env = env::expand(s);
+
+    let derive_serde = if s.attrs.serde {
+        Some(quote!(#[derive(::serde::Serialize, ::serde::Deserialize)]))
+    } else {
+        None
+    };
+
+    let (derives, debug) = inspect::derives(s);
+    let inspect = inspect::methods(s);
+
+    let builder_fn = s.builder_fn();
+    let constness = s.constness();
+    let build_const = constant::expand(s);
+    let builder_doc = doc::builder(s);
+    let builder_fn_doc = doc::builder_fn(s);
+    let build_doc = doc::build_fn(s);
+    let invalid = fallible::storage(s);
+    let invalid_init = fallible::init(s);
+    let invalid_check = fallible::check(s);
+    let assertions = flatten::assertions(s);
+    let (marker, marker_init) = s.marker();
+
+    quote! {
+        #builder_doc
+        #derive_clone
+        #derive_serde
+        #derives
+        #vis struct #builder #generics #where_clause {
+            #(#storage,)*
+            #invalid
+            #marker
+        }
+
+        impl #impl_generics #ident #ty_generics #where_clause {
+            #builder_fn_doc
+            #vis #constness fn #builder_fn() -> #builder #ty_generics {
+                #builder {
+                    #(#init,)*
+                    #invalid_init
+                    #marker_init
+                }
+            }
+        }
+
+        impl #impl_generics #builder #ty_generics #where_clause {
+            #(#setters)*
+
+            #merge
+
+            #inspect
+
+            #missing_fields
+
+            #build_doc
+            #[allow(deprecated)]
+            #vis fn build(#receiver) -> #core::result::Result<#ident #ty_generics, #error_ty>
+            where
+                #(#build_bounds,)*
+            {
+                #assertions
+                #invalid_check
+                #checks
+                #(#group_checks)*
+                #validate
+                #(#bindings)*
+                #core::result::Result::Ok(#construct)
+            }
+        }
+
+        #conversions
+
+        #from_env
+
+        #build_const
+
+        #debug
+
+        #error
+    }
+}
+
+/// `From<T>` for the builder and, when the builder clones in `build()`,
+/// `T::to_builder()`.
+fn conversions(s: &Struct, clone_bounds: &[TokenStream]) -> TokenStream {
+    let core = s.std.core();
+    let Struct {
+        ident,
+        vis,
+        generics,
+        builder,
+        ..
+    } = s;
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let invalid = fallible::init(s);
+
+    let from = s.fields.iter().map(|f| {
+        let name = &f.ident;
+        let member = &f.member;
+        let stored = f.stored(quote!(value.#member));
+        quote!(#name: #stored)
+    });
+
+    // Only for the builders that derive Clone.
+    let to_builder = match s.attrs.pattern {
+        Pattern::Owned => None,
+        Pattern::Mutable | Pattern::Immutable => {
+            let fields = s.fields.iter().map(|f| {
+                let name = &f.ident;
+                let member = &f.member;
+                let stored = match f.kind {
+                    // The nested value need not be Clone.
+                    Kind::Sub(..) => {
+                        let to_builder = flatten::nested(f.ty, "__to_builder");
+                        quote!(#core::option::Option::Some(#to_builder(&self.#member)))
+                    }
+                    _ => f.stored(quote!(#core::clone::Clone::clone(&self.#member))),
+                };
+                quote!(#name: #stored)
+            });
+            let doc = format!(
+                "Returns a [`{}`] with every field set to this value's.",
+                builder,
+            );
+            let bounds = clone_bounds;
+            Some(quote! {
+                impl #impl_generics #ident #ty_generics #where_clause {
+                    #[doc = #doc]
+                    #[allow(deprecated)]
+                    #vis fn to_builder(&self) -> #builder #ty_generics
+                    where
+                        #(#bounds,)*
+                    {
+                        #builder {
+                            #(#fields,)*
+                            #invalid
+                        }
+                    }
+                }
+            })
+        }
+    };
+
+    quote! {
+        impl #impl_generics #core::convert::From<#ident #ty_generics> for #builder #ty_generics
+        #where_clause
+        {
+            #[allow(deprecated)]
+            fn from(value: #ident #ty_generics) -> Self {
+                #builder {
+                    #(#from,)*
+                    #invalid
+                }
+            }
+        }
+
+        #to_builder
+    }
+}
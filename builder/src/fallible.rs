@@ -0,0 +1,186 @@
+// `try_<field>` setters for fields with `#[builder(setter(try_into))]`, which
+// take anything that converts into the field's type with `TryInto`.
+//
+// By default the setter returns the conversion's error itself, wrapping the
+// builder in a `Result`. With `try_into = "deferred"` it returns the builder
+// like any other setter, so that a chain of setters is not interrupted, and
+// keeps the failure in a hidden field of the builder, one for each such field,
+// for `build()` to return as `InvalidValue`. The field itself is left as it
+// was. Setting the field again, or unsetting it, drops the failure.
+//
+// Typestate builders have no error to return from `build()` and only get the
+// first kind, which for a required field changes the builder's state on
+// success as its other setter does.
+
+use crate::attr::Fallible;
+use crate::expand::{Field, Struct};
+use crate::setter::Setter;
+use crate::{doc, error};
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::Ident;
+
+/// `try_port` for the field `port`.
+pub fn ident(f: &Field) -> Ident {
+    let unraw = f.ident.to_string();
+    format_ident!("try_{}", unraw.trim_start_matches("r#"))
+}
+
+/// The `try_<field>` setter.
+pub fn setter<'a>(f: &'a Field, fallible: Fallible) -> Setter<'a> {
+    let (core, alloc) = (f.std.core(), f.std.alloc());
+    let name = &f.ident;
+    let ty = f.setter_ty();
+    let bound = quote!(__V: #core::convert::TryInto<#ty>);
+    let error = quote!(<__V as #core::convert::TryInto<#ty>>::Error);
+    let attrs = doc::try_setter(f, fallible);
+    match fallible {
+        Fallible::Immediate => {
+            let mut setter = Setter::new(
+                &ident(f),
+                quote!(<__V>),
+                quote!(#name: __V),
+                vec![bound],
+                move |b| {
+                    quote! {
+                        #b.#name = #core::option::Option::Some(
+                            #core::convert::TryInto::try_into(#name)?,
+                        )
+                    }
+                },
+            );
+            setter.error = Some(error);
+            setter.with_attrs(attrs)
+        }
+        Fallible::Deferred => {
+            let invalid = invalid(f);
+            Setter::new(
+                &ident(f),
+                quote!(<__V>),
+                quote!(#name: __V),
+                vec![bound, quote!(#error: #core::fmt::Display)],
+                move |b| {
+                    quote! {
+                        match #core::convert::TryInto::try_into(#name) {
+                            #core::result::Result::Ok(value) => {
+                                #b.#name = #core::option::Option::Some(value);
+                                #b.#invalid = #core::option::Option::None;
+                            }
+                            #core::result::Result::Err(error) => {
+                                #b.#invalid = #core::option::Option::Some(
+                                    #alloc::string::ToString::to_string(&error),
+                                );
+                            }
+                        }
+                    }
+                },
+            )
+            .with_attrs(attrs)
+        }
+    }
+}
+
+/// `__invalid_port`, the hidden field holding the message of the failure to
+/// convert a value for the deferred field `port`.
+fn invalid(f: &Field) -> Ident {
+    format_ident!("__invalid_{}", f.ident)
+}
+
+/// The fields with `setter(try_into = "deferred")`.
+fn deferred<'a>(s: &'a Struct) -> impl Iterator<Item = &'a Field<'a>> {
+    s.fields
+        .iter()
+        .filter(|f| f.attrs.try_into == Some(Fallible::Deferred))
+}
+
+/// Whether any field has `setter(try_into = "deferred")`, in which case the
+/// builder has hidden `__invalid_<field>` fields.
+pub fn has_deferred(s: &Struct) -> bool {
+    deferred(s).next().is_some()
+}
+
+/// The declarations of the hidden fields.
+pub fn storage(s: &Struct) -> TokenStream {
+    let (core, alloc) = (s.std.core(), s.std.alloc());
+    let serde = if s.attrs.serde {
+        Some(quote!(#[serde(skip)]))
+    } else {
+        None
+    };
+    deferred(s)
+        .map(|f| {
+            let invalid = invalid(f);
+            quote! {
+                #serde
+                #invalid: #core::option::Option<#alloc::string::String>,
+            }
+        })
+        .collect()
+}
+
+/// The initializers of the hidden fields in a new builder.
+pub fn init(s: &Struct) -> TokenStream {
+    let core = s.std.core();
+    deferred(s)
+        .map(|f| {
+            let invalid = invalid(f);
+            quote!(#invalid: #core::option::Option::None,)
+        })
+        .collect()
+}
+
+/// Statement dropping the failure kept by `builder` for `f`, when `f` is set
+/// or unset by another setter.
+pub fn clear(f: &Field, builder: &TokenStream) -> TokenStream {
+    let core = f.std.core();
+    if f.attrs.try_into != Some(Fallible::Deferred) {
+        return TokenStream::new();
+    }
+    let invalid = invalid(f);
+    quote!(#builder.#invalid = #core::option::Option::None;)
+}
+
+/// Statements clearing the failures kept by `builder` in `reset()`.
+pub fn reset(s: &Struct, builder: &TokenStream) -> TokenStream {
+    deferred(s).map(|f| clear(f, builder)).collect()
+}
+
+/// Statements in `merge()` that take `other`'s failure for each field that
+/// `other` has set or failed to set. They come before the fields are moved
+/// out of `other`.
+pub fn merge(s: &Struct, builder: &TokenStream) -> TokenStream {
+    deferred(s)
+        .map(|f| {
+            let name = &f.ident;
+            let invalid = invalid(f);
+            quote! {
+                if other.#name.is_some() || other.#invalid.is_some() {
+                    #builder.#invalid = other.#invalid;
+                }
+            }
+        })
+        .collect()
+}
+
+/// Statements in `build()` that return the kept failure of the first field
+/// that has one.
+pub fn check(s: &Struct) -> TokenStream {
+    let core = s.std.core();
+    let checks = deferred(s).map(|f| {
+        let field = f.ident.to_string();
+        let invalid = invalid(f);
+        let bail = error::bail(
+            s,
+            quote!(InvalidValue {
+                field: #field,
+                message: #core::clone::Clone::clone(message),
+            }),
+        );
+        quote! {
+            if let #core::option::Option::Some(message) = &self.#invalid {
+                #bail;
+            }
+        }
+    });
+    quote!(#(#checks)*)
+}
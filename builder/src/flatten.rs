@@ -0,0 +1,510 @@
+// `#[builder(flatten)]`: a field whose setters are those of its own builder,
+// forwarded by the builder of the struct it is in.
+//
+// Every struct with a builder comes with a hidden `__{Struct}Nested` trait,
+// since any of them may be a `sub_builder`, through which outer builders reach a `sub_builder`
+// or `flatten` field's builder. Only a builder that is Clone and takes its
+// setters by reference can be kept by another builder. Of one that cannot, the
+// trait gives `()` instead, so that the assertion the outer builder makes at
+// the field is the one error of nesting an owned or typestate builder.
+//
+// The derive for the outer struct cannot see the fields of the flattened one,
+// so a struct with `#[builder(flattenable)]` comes with a hidden
+// `macro_rules!` that the outer builder invokes. It is re-exported under the
+// builder's name, so that the `ConfigBuilder` that `flatten` (like
+// `sub_builder`) needs to be able to name brings it along. A `macro_rules!`
+// cannot be re-exported out of its crate, so only structs of the same crate
+// can be flattened; a field whose path starts with `::` is reported as such. Given the outer builder's pattern and field, the macro
+// expands to the forwarding setters. An owned builder holding the owned one it
+// flattens also merges the two through it, since only the macro knows their
+// `merge()`.
+//
+// The forwarding setters are expanded where the outer struct is, where the
+// types of the flattened struct's fields may not be in scope. So each of them
+// is generic over its arguments and calls the setter it forwards to through a
+// hidden `__{Builder}Setter` trait, implemented next to that setter once per
+// setter, which alone names the types.
+//
+// The outer derive in turn defines, for each flattened field, a macro that
+// every forwarding setter is passed through. One named like a setter of the
+// outer builder's own fields is replaced by errors at both fields. A clash
+// between two flattened fields is reported by the compiler as a duplicate
+// definition.
+
+use crate::attr::Pattern;
+use crate::error;
+use crate::expand::{respan, Field, Kind, Nesting, Struct, Target};
+use crate::setter::{self, Setter};
+use crate::ty;
+use proc_macro2::{Literal, TokenStream};
+use quote::{format_ident, quote, quote_spanned};
+use syn::parse::Parser;
+use syn::punctuated::Punctuated;
+use syn::spanned::Spanned;
+use syn::{parse_quote, FnArg, Ident, Path, PathArguments, Token, Type, TypePath};
+
+/// The trait that lets other builders nest this one and, if it is
+/// `flattenable`, the macro with which they flatten it.
+pub fn expand(s: &Struct) -> TokenStream {
+    let (core, alloc) = (s.std.core(), s.std.alloc());
+    match s.target {
+        Target::Struct => {}
+        Target::Variant(_) | Target::Fn(_) => return TokenStream::new(),
+    }
+
+    let ident = s.ident;
+    let vis = s.vis;
+    let nested = format_ident!("__{}Nested", ident);
+    let nestable = !s.attrs.typestate && s.attrs.pattern != Pattern::Owned;
+    let nested_impl = nested_impl(s, &nested, nestable);
+    let flattenable = if s.attrs.flattenable.is_some() {
+        Some(flattenable(s, nestable))
+    } else {
+        None
+    };
+
+    quote! {
+        #[doc(hidden)]
+        #vis trait #nested: #core::marker::Sized {
+            const __NESTABLE: bool;
+            type Builder;
+            type Error;
+            fn __builder() -> Self::Builder;
+            fn __from(self) -> Self::Builder;
+            fn __to_builder(&self) -> Self::Builder;
+            fn __missing(builder: &Self::Builder) -> #alloc::vec::Vec<#alloc::string::String>;
+            fn __build(builder: Self::Builder) -> #core::result::Result<Self, Self::Error>;
+            fn __merge(builder: Self::Builder, other: Self::Builder) -> Self::Builder;
+        }
+        #nested_impl
+        #flattenable
+    }
+}
+
+/// The macro of a `flattenable` builder, re-exported under the builder's
+/// name, and the `__{Builder}Setter` trait it calls the setters through.
+fn flattenable(s: &Struct, nestable: bool) -> TokenStream {
+    let builder = &s.builder;
+    let mac = format_ident!("__{}_flatten", builder);
+    // Only an owned builder can flatten an owned one, and the assertion at
+    // the field reports the others.
+    let forwarders = |pattern| {
+        if nestable || pattern == Pattern::Owned {
+            forwarders(s, pattern)
+        } else {
+            TokenStream::new()
+        }
+    };
+    let mutable = forwarders(Pattern::Mutable);
+    let owned = forwarders(Pattern::Owned);
+    let immutable = forwarders(Pattern::Immutable);
+    let merge = merge(s, quote!($builder), quote!($other));
+    let setter_trait = setter_trait(s);
+
+    quote! {
+        #setter_trait
+
+        #[doc(hidden)]
+        #[allow(unused_macros)]
+        macro_rules! #mac {
+            (merge $builder:ident $other:ident) => {
+                #merge
+            };
+            (mutable [$vis:vis] $field:ident $ty:ty [$($path:tt)*] $check:ident) => {
+                #mutable
+            };
+            (owned [$vis:vis] $field:ident $ty:ty [$($path:tt)*] $check:ident) => {
+                #owned
+            };
+            (immutable [$vis:vis] $field:ident $ty:ty [$($path:tt)*] $check:ident) => {
+                #immutable
+            };
+        }
+
+        #[doc(hidden)]
+        #[allow(unused_imports)]
+        pub(crate) use #mac as #builder;
+    }
+}
+
+/// The impl of `__{Struct}Nested`. The builder of a struct that cannot be
+/// nested is `()`, whose functions are never called: a builder nesting it
+/// fails the assertion at that field, and nothing else, since `()` is all
+/// that the builder needs its nested builder to be.
+fn nested_impl(s: &Struct, nested: &Ident, nestable: bool) -> TokenStream {
+    let (core, alloc) = (s.std.core(), s.std.alloc());
+    let ident = s.ident;
+    let builder = &s.builder;
+    let mut generics = s.generics.clone();
+    if nestable {
+        let where_clause = generics.make_where_clause();
+        for bound in s.clone_bounds() {
+            where_clause.predicates.push(parse_quote!(#bound));
+        }
+    }
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let missing = quote!(#alloc::vec::Vec<#alloc::string::String>);
+    let items = if nestable {
+        let error = error::return_ty(s);
+        let fields = s.missing(&quote!(builder), |f| matches!(f.kind, Kind::Sub(..)));
+        let merge = merge(s, quote!(builder), quote!(other));
+        quote! {
+            const __NESTABLE: bool = true;
+            type Builder = #builder #ty_generics;
+            type Error = #error;
+            fn __builder() -> Self::Builder {
+                Self::builder()
+            }
+            fn __from(self) -> Self::Builder {
+                #core::convert::From::from(self)
+            }
+            fn __to_builder(&self) -> Self::Builder {
+                Self::to_builder(self)
+            }
+            fn __missing(builder: &Self::Builder) -> #missing {
+                #fields
+            }
+            fn __build(builder: Self::Builder) -> #core::result::Result<Self, Self::Error> {
+                builder.build()
+            }
+            fn __merge(builder: Self::Builder, other: Self::Builder) -> Self::Builder {
+                #merge
+            }
+        }
+    } else {
+        quote! {
+            const __NESTABLE: bool = false;
+            type Builder = ();
+            type Error = #core::convert::Infallible;
+            fn __builder() -> Self::Builder {}
+            fn __from(self) -> Self::Builder {}
+            fn __to_builder(&self) -> Self::Builder {}
+            fn __missing(_: &Self::Builder) -> #missing {
+                #alloc::vec::Vec::new()
+            }
+            fn __build(_: Self::Builder) -> #core::result::Result<Self, Self::Error> {
+                #core::unreachable!()
+            }
+            fn __merge(_: Self::Builder, _: Self::Builder) -> Self::Builder {}
+        }
+    };
+    quote! {
+        #[allow(deprecated)]
+        impl #impl_generics #nested for #ident #ty_generics #where_clause {
+            #items
+        }
+    }
+}
+
+/// `other` merged into `builder`, both builders taken by value. A builder
+/// whose `merge()` is shadowed by a setter, which is an error already, is
+/// replaced as a whole.
+fn merge(s: &Struct, builder: TokenStream, other: TokenStream) -> TokenStream {
+    match s.attrs.pattern {
+        _ if !s.has_method("merge") => other,
+        Pattern::Mutable => quote!({
+            let mut builder = #builder;
+            builder.merge(#other);
+            builder
+        }),
+        Pattern::Owned | Pattern::Immutable => quote!(#builder.merge(#other)),
+    }
+}
+
+/// The assertions, at each `sub_builder` or `flatten` field that is nested
+/// through `__{Struct}Nested`, that its builder can be nested, as statements
+/// of `build()`. They are evaluated as the crate is checked unless the struct
+/// is generic, in which case it is when `build()` is used.
+pub fn assertions(s: &Struct) -> TokenStream {
+    let core = s.std.core();
+    s.fields
+        .iter()
+        .filter(|f| matches!(f.kind, Kind::Sub(_, Nesting::Trait)))
+        .map(|f| {
+            let nestable = nested(f.ty, "__NESTABLE");
+            let path = macro_path(f);
+            let builder = match path.segments.last() {
+                Some(segment) => segment.ident.to_string(),
+                None => unreachable!(),
+            };
+            let message = if f.attrs.flatten {
+                format!(
+                    "`{}` cannot be flattened into `{}`: only builders with the mutable or \
+                     immutable pattern can be flattened into one without the owned pattern",
+                    builder, s.builder,
+                )
+            } else {
+                format!(
+                    "`{}` cannot be a sub-builder: only a builder with the mutable or \
+                     immutable pattern and no typestate can be nested",
+                    builder,
+                )
+            };
+            let span = f.ty.span();
+            let assert = respan(quote!(#core::assert!(#nestable, #message)), span);
+            if s.generics.params.is_empty() {
+                quote_spanned!(span=> const _: () = #assert;)
+            } else {
+                quote_spanned!(span=> const { #assert };)
+            }
+        })
+        .collect()
+}
+
+/// The setters that are forwarded, along with their field: those of every
+/// field that is not flattened itself, which are not forwarded any further.
+/// Their index is that of their `__{Builder}Setter` impl.
+fn forwarded<'a>(s: &'a Struct) -> impl Iterator<Item = (&'a Field<'a>, Setter<'a>)> {
+    s.fields.iter().filter(|f| !f.attrs.flatten).flat_map(|f| {
+        setter::setters(f)
+            .into_iter()
+            .map(move |setter| (f, setter))
+    })
+}
+
+/// The `__{Builder}Setter` trait, through which the forwarding setter with
+/// index `I` calls its setter, given its arguments as the tuple `A`, and its
+/// impls.
+fn setter_trait(s: &Struct) -> TokenStream {
+    let core = s.std.core();
+    let vis = s.vis;
+    let builder = &s.builder;
+    let ident = format_ident!("__{}Setter", builder);
+    let (receiver, output) = match s.attrs.pattern {
+        Pattern::Mutable => (quote!(&mut self), quote!(())),
+        Pattern::Owned | Pattern::Immutable => (quote!(self), quote!(Self)),
+    };
+    let impls = forwarded(s).enumerate().map(|(i, (_, inner))| {
+        let index = Literal::usize_unsuffixed(i);
+        let name = &inner.name;
+        let generics = &inner.generics;
+        let bounds = &inner.bounds;
+        let (pats, tys) = args(&inner.args);
+        let call = match inner.error {
+            Some(_) => quote!(self.#name(#(#pats),*)?),
+            None => quote!(self.#name(#(#pats),*)),
+        };
+        let value = match s.attrs.pattern {
+            Pattern::Mutable => quote!({ #call; }),
+            Pattern::Owned | Pattern::Immutable => call,
+        };
+        let error = match &inner.error {
+            Some(error) => error.clone(),
+            None => quote!(#core::convert::Infallible),
+        };
+        quote! {
+            #[allow(deprecated)]
+            impl #generics #ident<#index, (#(#tys,)*)> for #builder where #(#bounds,)* {
+                type Error = #error;
+                fn __set(
+                    #receiver,
+                    (#(#pats,)*): (#(#tys,)*),
+                ) -> #core::result::Result<#output, Self::Error> {
+                    #core::result::Result::Ok(#value)
+                }
+            }
+        }
+    });
+    quote! {
+        #[doc(hidden)]
+        #vis trait #ident<const I: usize, A>: Sized {
+            type Error;
+            fn __set(#receiver, args: A) -> #core::result::Result<#output, Self::Error>;
+        }
+        #(#impls)*
+    }
+}
+
+/// This builder's setters as methods of an outer builder in `pattern`, which
+/// holds this one in its field `$field` of type `Option<Builder>`. Each is
+/// passed through the outer builder's `$check` macro.
+fn forwarders(s: &Struct, pattern: Pattern) -> TokenStream {
+    let core = s.std.core();
+    let vis = quote!($vis);
+    let builder = &s.builder;
+    let ident = format_ident!("__{}Setter", builder);
+    forwarded(s)
+        .enumerate()
+        .map(|(i, (f, inner))| {
+            let name = &inner.name;
+            let (pats, _) = args(&inner.args);
+            let params: Vec<_> = (0..pats.len()).map(|i| format_ident!("__A{}", i)).collect();
+            let index = Literal::usize_unsuffixed(i);
+            let setter_trait = quote!($($path)* #ident<#index, (#(#params,)*)>);
+            let call = quote!(<$($path)* #builder as #setter_trait>::__set);
+            let (bound, error) = match inner.error {
+                Some(_) => (
+                    quote!($($path)* #builder: #setter_trait),
+                    Some(quote!(<$($path)* #builder as #setter_trait>::Error)),
+                ),
+                None => (
+                    quote! {
+                        $($path)* #builder: $($path)* #ident<
+                            #index,
+                            (#(#params,)*),
+                            Error = #core::convert::Infallible,
+                        >
+                    },
+                    None,
+                ),
+            };
+            let fallible = error.is_some();
+            let tuple = quote!((#(#pats,)*));
+            let set = move |target: TokenStream| {
+                if fallible {
+                    quote!(#call(#target, #tuple)?)
+                } else {
+                    quote! {
+                        match #call(#target, #tuple) {
+                            #core::result::Result::Ok(value) => value,
+                            #core::result::Result::Err(error) => match error {},
+                        }
+                    }
+                }
+            };
+            let update = move |b: TokenStream| match s.attrs.pattern {
+                Pattern::Mutable => set(quote!(#b.$field.get_or_insert_with(<$ty>::builder))),
+                Pattern::Owned | Pattern::Immutable => {
+                    let set = set(quote! {
+                        #core::option::Option::unwrap_or_else(#b.$field.take(), <$ty>::builder)
+                    });
+                    quote!(#b.$field = #core::option::Option::Some(#set))
+                }
+            };
+            let mut outer = Setter::new(
+                name,
+                quote!(<#(#params),*>),
+                quote!(#(#pats: #params),*),
+                vec![bound],
+                update,
+            )
+            .with_attrs(inner.attrs);
+            outer.error = error;
+            if pattern == Pattern::Immutable {
+                outer.bounds.push(quote!(Self: #core::clone::Clone));
+            }
+            // Spanned at the field, so that a clash with another flattened
+            // field points there rather than at this derive.
+            let method = respan(
+                setter::method(&vis, pattern, None, s.std, &outer),
+                name.span(),
+            );
+            let message = format!(
+                "setter `{}` of `{}` clashes with a setter of the builder it is flattened into",
+                name, builder,
+            );
+            let error = respan(quote!(#core::compile_error!(#message);), f.ident.span());
+            quote! {
+                $check!(#name { #method } { #error });
+            }
+        })
+        .collect()
+}
+
+/// `[a, b]` and `[A, B]` for the arguments `a: A, b: B`.
+fn args(args: &TokenStream) -> (Vec<TokenStream>, Vec<TokenStream>) {
+    let parser = Punctuated::<FnArg, Token![,]>::parse_terminated;
+    let args = parser.parse2(args.clone()).unwrap_or_default();
+    args.into_iter()
+        .filter_map(|arg| match arg {
+            FnArg::Typed(arg) => {
+                let (pat, ty) = (arg.pat, arg.ty);
+                Some((quote!(#pat), quote!(#ty)))
+            }
+            FnArg::Receiver(_) => None,
+        })
+        .unzip()
+}
+
+/// For each flattened field, the macro that its forwarding setters are passed
+/// through. Those named like a setter of the builder's own fields are
+/// replaced by errors, at that field and at the flattened struct's field.
+pub fn checks(s: &Struct) -> TokenStream {
+    let core = s.std.core();
+    s.fields
+        .iter()
+        .filter(|f| f.attrs.flatten)
+        .map(|flattened| {
+            let mac = check_ident(s, flattened);
+            let arms = forwarded(s).map(|(f, setter)| {
+                let name = &setter.name;
+                let message = format!(
+                    "setter `{}` clashes with one of the flattened field `{}`",
+                    name, flattened.ident,
+                );
+                let error = respan(quote!(#core::compile_error!(#message);), f.ident.span());
+                quote! {
+                    (#name { $($setter:tt)* } { $($error:tt)* }) => { $($error)* #error };
+                }
+            });
+            quote! {
+                #[doc(hidden)]
+                #[allow(unused_macros)]
+                macro_rules! #mac {
+                    #(#arms)*
+                    ($name:ident { $($setter:tt)* } { $($error:tt)* }) => { $($setter)* };
+                }
+            }
+        })
+        .collect()
+}
+
+/// `__ServerBuilder_net_setters` for the field `net` of `Server`.
+fn check_ident(s: &Struct, f: &Field) -> Ident {
+    format_ident!("__{}_{}_setters", s.builder, f.ident)
+}
+
+/// The invocation of the flattened builder's macro that forwards its setters.
+pub fn setters(s: &Struct, f: &Field) -> TokenStream {
+    let name = &f.ident;
+    let ty = f.ty;
+    let mac = macro_path(f);
+    let vis = s.vis;
+    let pattern = match s.attrs.pattern {
+        Pattern::Mutable => quote!(mutable),
+        Pattern::Owned => quote!(owned),
+        Pattern::Immutable => quote!(immutable),
+    };
+    // The path of the module the builder is in, as in `net::`.
+    let leading = &mac.leading_colon;
+    let modules = mac.segments.iter().rev().skip(1).rev();
+    let check = check_ident(s, f);
+    quote! {
+        #mac!(#pattern [#vis] #name #ty [#leading #(#modules::)*] #check);
+    }
+}
+
+/// The merge of the nested builders `builder` and `other` of a field that
+/// holds the builder it flattens, which its macro knows the pattern of.
+pub fn merge_held(f: &Field, builder: &Ident, other: &Ident) -> TokenStream {
+    let mac = macro_path(f);
+    quote!(#mac!(merge #builder #other))
+}
+
+/// `<Config as __ConfigNested>::item` for a field of type `Config`, spanned
+/// at the field's type so that a missing impl is reported there.
+pub fn nested(ty: &Type, item: &str) -> TokenStream {
+    let mut path = match ty {
+        Type::Path(TypePath { path, .. }) => path.clone(),
+        _ => unreachable!(),
+    };
+    if let Some(segment) = path.segments.last_mut() {
+        segment.ident = format_ident!("__{}Nested", segment.ident);
+        segment.arguments = PathArguments::None;
+    }
+    let item = format_ident!("{}", item);
+    quote_spanned!(ty.span()=> <#ty as #path>::#item)
+}
+
+/// The builder's path without generic arguments, which is also the path of
+/// its macro.
+fn macro_path(f: &Field) -> Path {
+    let mut path = match ty::builder_of(f.ty) {
+        Some(Type::Path(TypePath { path, .. })) => path,
+        _ => unreachable!(),
+    };
+    for segment in &mut path.segments {
+        segment.arguments = PathArguments::None;
+    }
+    path
+}
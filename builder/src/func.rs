@@ -0,0 +1,456 @@
+// Expansion for the `#[derive_builder::builder]` attribute on functions.
+//
+// The parameters of the function become the fields of a typestate builder, as
+// if they had been declared on a struct with `#[builder(typestate)]`, and
+// `call()` passes them on to the function. On a free function `connect` the
+// builder is returned by a new function `connect_builder()`. Methods are
+// marked with `#[builder]` inside an inherent impl block carrying the
+// attribute, since the builder struct cannot be declared inside the impl, and
+// `client.send_builder()` keeps the receiver in the builder until `call()`.
+//
+// Parameter types are copied into the builder struct, which lives outside the
+// impl block. `Self` is replaced with the impl's type, and lifetimes elided in
+// them become new lifetime parameters of the builder, `'__0`, `'__1` and so on,
+// like the `'__recv` of an elided receiver.
+
+use crate::attr::Errors;
+use crate::expand::{self, Field, Struct, Target};
+use proc_macro2::{Span, TokenStream};
+use quote::{format_ident, quote};
+use syn::visit_mut::{self, VisitMut};
+use syn::{
+    parse_quote, Attribute, Data, DeriveInput, Error, FnArg, GenericParam, Generics, Ident,
+    ImplItem, Item, ItemImpl, Lifetime, Pat, Result, ReturnType, Signature, Token, Type,
+    Visibility, WherePredicate,
+};
+
+/// How `call()` calls the function.
+pub struct Call {
+    /// The function's name.
+    pub ident: Ident,
+    /// `connect`, or `<Client>::send` for a method.
+    pub path: TokenStream,
+    /// The method's receiver, kept in the builder.
+    pub receiver: Option<Receiver>,
+    /// The type returned by `call()`.
+    pub output: TokenStream,
+    pub asyncness: Option<Token![async]>,
+    pub unsafety: Option<Token![unsafe]>,
+    /// Generic parameters of the function returning the builder, besides
+    /// those of the impl block it is in.
+    pub generics: Generics,
+}
+
+pub struct Receiver {
+    /// The receiver as written in the builder's constructor, like
+    /// `&'__recv self`.
+    pub arg: TokenStream,
+    /// The type of the builder field holding it, like `&'__recv Client`.
+    pub ty: TokenStream,
+}
+
+impl Call {
+    /// The call of the function with every field as an argument, in order.
+    pub fn invoke(&self, fields: &[Field]) -> TokenStream {
+        let path = &self.path;
+        let receiver = self.receiver.as_ref().map(|_| quote!(self.__receiver,));
+        let args = fields.iter().map(|f| &f.ident);
+        let await_ = self.asyncness.map(|_| quote!(.await));
+        quote!(#path(#receiver #(#args),*) #await_)
+    }
+}
+
+/// The item with its builders. The item is emitted even if some of them
+/// cannot be generated, so that only the builders are missing.
+pub fn expand(args: TokenStream, item: Item) -> TokenStream {
+    let mut errors = Errors::default();
+    let tokens = match item {
+        Item::Fn(mut item) => {
+            let attrs: Vec<Attribute> = if args.is_empty() {
+                Vec::new()
+            } else {
+                vec![parse_quote!(#[builder(#args)])]
+            };
+            match function(&mut item.sig, &item.vis, None, &attrs) {
+                Ok((builder, constructor)) => quote! {
+                    #item
+                    #constructor
+                    #builder
+                },
+                Err(error) => {
+                    errors.push(error);
+                    quote!(#item)
+                }
+            }
+        }
+        Item::Impl(item) => {
+            if !args.is_empty() {
+                errors.push(Error::new_spanned(
+                    args,
+                    "options go on the #[builder] of each method",
+                ));
+            }
+            methods(item, &mut errors)
+        }
+        item => {
+            errors.push(Error::new_spanned(
+                &item,
+                "#[builder] applies to functions and inherent impl blocks",
+            ));
+            quote!(#item)
+        }
+    };
+    let errors = errors.to_compile_error();
+    quote! {
+        #tokens
+        #errors
+    }
+}
+
+fn methods(mut item: ItemImpl, errors: &mut Errors) -> TokenStream {
+    if let Some((_, path, _)) = &item.trait_ {
+        errors.push(Error::new_spanned(
+            path,
+            "#[builder] cannot add methods to a trait impl; use an inherent impl block",
+        ));
+    }
+
+    let owner = item.clone();
+    let mut builders = Vec::new();
+    let mut constructors = Vec::new();
+
+    for impl_item in &mut item.items {
+        let method = match impl_item {
+            ImplItem::Method(method) => method,
+            _ => continue,
+        };
+        let marked = take_builder_attrs(&mut method.attrs);
+        if marked.is_empty() {
+            continue;
+        }
+        // Trait impls only get the attributes removed.
+        if item.trait_.is_some() {
+            for input in &mut method.sig.inputs {
+                if let FnArg::Typed(arg) = input {
+                    take_builder_attrs(&mut arg.attrs);
+                }
+            }
+            continue;
+        }
+        // A bare `#[builder]` only marks the method.
+        let options: Vec<Attribute> = marked
+            .into_iter()
+            .filter(|attr| !attr.tokens.is_empty())
+            .collect();
+        match function(&mut method.sig, &method.vis, Some(&owner), &options) {
+            Ok((builder, constructor)) => {
+                builders.push(builder);
+                constructors.push(constructor);
+            }
+            Err(error) => errors.push(error),
+        }
+    }
+
+    item.items
+        .extend(constructors.into_iter().map(ImplItem::Verbatim));
+
+    quote! {
+        #item
+        #(#builders)*
+    }
+}
+
+/// Removes the `#[builder]` attributes from `attrs` and returns them.
+fn take_builder_attrs(attrs: &mut Vec<Attribute>) -> Vec<Attribute> {
+    let (marked, rest) = attrs
+        .drain(..)
+        .partition(|attr| attr.path.is_ident("builder"));
+    *attrs = rest;
+    marked
+}
+
+/// Returns the builder for the function with signature `sig`, and the
+/// function returning it. The builder's attributes are removed from `sig`.
+fn function(
+    sig: &mut Signature,
+    vis: &Visibility,
+    owner: Option<&ItemImpl>,
+    attrs: &[Attribute],
+) -> Result<(TokenStream, TokenStream)> {
+    let self_ty = owner.map(|owner| &*owner.self_ty);
+    let recv = Lifetime::new("'__recv", sig.ident.span());
+    let mut receiver = None;
+    let mut new_lifetime = false;
+    let mut receiver_lifetime = None;
+    let mut fields = Vec::new();
+    let mut errors = Vec::new();
+    if let Some(variadic) = &sig.variadic {
+        errors.push(Error::new_spanned(
+            variadic,
+            "variadic functions have no builder",
+        ));
+    }
+    let mut stored = Rewrite {
+        stored: true,
+        ..Rewrite::new(self_ty)
+    };
+
+    for input in &mut sig.inputs {
+        match input {
+            FnArg::Receiver(arg) => {
+                let self_ty = match self_ty {
+                    Some(self_ty) => self_ty,
+                    None => {
+                        errors.push(Error::new_spanned(arg, "`self` outside of an impl block"));
+                        continue;
+                    }
+                };
+                let mutability = &arg.mutability;
+                receiver = Some(match &arg.reference {
+                    Some((_, lifetime)) => {
+                        let lifetime = match lifetime {
+                            Some(lifetime) => lifetime.clone(),
+                            None => {
+                                new_lifetime = true;
+                                recv.clone()
+                            }
+                        };
+                        receiver_lifetime = Some(lifetime.clone());
+                        Receiver {
+                            arg: quote!(&#lifetime #mutability self),
+                            ty: quote!(&#lifetime #mutability #self_ty),
+                        }
+                    }
+                    None => Receiver {
+                        arg: quote!(self),
+                        ty: quote!(#self_ty),
+                    },
+                });
+            }
+            FnArg::Typed(arg) => {
+                let builder_attrs = take_builder_attrs(&mut arg.attrs);
+                let ident = match &*arg.pat {
+                    Pat::Ident(pat) if pat.by_ref.is_none() && pat.subpat.is_none() => {
+                        pat.ident.clone()
+                    }
+                    pat => {
+                        errors.push(Error::new_spanned(
+                            pat,
+                            "builder parameters must be plain names, which become the setters",
+                        ));
+                        continue;
+                    }
+                };
+                if ident == "self" {
+                    errors.push(Error::new_spanned(
+                        arg,
+                        "typed `self` receivers are not supported; use `self`, `&self` or \
+                         `&mut self`",
+                    ));
+                    continue;
+                }
+                let mut ty = (*arg.ty).clone();
+                stored.visit_type_mut(&mut ty);
+                fields.push(quote!(#(#builder_attrs)* #ident: #ty));
+            }
+        }
+    }
+
+    errors.append(&mut stored.errors);
+    if let Some(error) = errors.into_iter().reduce(|mut all, error| {
+        all.combine(error);
+        all
+    }) {
+        return Err(error);
+    }
+
+    let mut output = match &sig.output {
+        ReturnType::Default => parse_quote!(()),
+        ReturnType::Type(_, ty) => (**ty).clone(),
+    };
+    // Lifetimes elided in the return type come from a `&self` receiver, or
+    // else from the only lifetime in the parameters.
+    let mut rewrite = Rewrite::new(self_ty);
+    rewrite.elided = match &receiver_lifetime {
+        Some(lifetime) => Some(lifetime),
+        None if stored.lifetimes.len() == 1 => stored.lifetimes.first(),
+        None => None,
+    };
+    rewrite.visit_type_mut(&mut output);
+
+    // The constructor declares the method's own generic parameters, and the
+    // builder also has those of the impl block.
+    let mut generics = sig.generics.clone();
+    for fresh in stored.fresh.iter().rev() {
+        generics.params.insert(0, parse_quote!(#fresh));
+    }
+    if new_lifetime {
+        generics.params.insert(0, parse_quote!(#recv));
+    }
+    Rewrite::new(self_ty).visit_generics_mut(&mut generics);
+
+    let mut params: Vec<GenericParam> = generics.params.iter().cloned().collect();
+    let mut predicates: Vec<WherePredicate> = generics
+        .where_clause
+        .iter()
+        .flat_map(|clause| clause.predicates.iter().cloned())
+        .collect();
+    if let Some(owner) = owner {
+        params.extend(owner.generics.params.iter().cloned());
+        predicates.extend(
+            owner
+                .generics
+                .where_clause
+                .iter()
+                .flat_map(|clause| clause.predicates.iter().cloned()),
+        );
+    }
+    // Lifetimes must come first.
+    params.sort_by_key(|param| !matches!(param, GenericParam::Lifetime(_)));
+
+    let turbofish: Vec<&Ident> = sig
+        .generics
+        .params
+        .iter()
+        .filter_map(|param| match param {
+            GenericParam::Type(param) => Some(&param.ident),
+            GenericParam::Const(param) => Some(&param.ident),
+            GenericParam::Lifetime(_) => None,
+        })
+        .collect();
+    let fn_ident = &sig.ident;
+    let fn_path = if turbofish.is_empty() {
+        quote!(#fn_ident)
+    } else {
+        quote!(#fn_ident::<#(#turbofish),*>)
+    };
+    let path = match self_ty {
+        Some(self_ty) => quote!(<#self_ty>::#fn_path),
+        None => fn_path,
+    };
+
+    let name = match self_ty {
+        Some(Type::Path(ty)) => match ty.path.segments.last() {
+            Some(segment) => format!("{}{}", segment.ident, expand::camel_case(fn_ident)),
+            None => expand::camel_case(fn_ident),
+        },
+        _ => expand::camel_case(fn_ident),
+    };
+    let name = format_ident!("{}", name, span = fn_ident.span());
+
+    let input: DeriveInput = parse_quote! {
+        #vis struct #name <#(#params),*>
+        where
+            #(#predicates,)*
+        {
+            #(#fields,)*
+        }
+    };
+    let data = match &input.data {
+        Data::Struct(data) => &data.fields,
+        _ => unreachable!(),
+    };
+
+    let mut attrs = attrs.to_vec();
+    attrs.push(parse_quote!(#[builder(typestate)]));
+    let call = Call {
+        ident: fn_ident.clone(),
+        path,
+        receiver,
+        output: quote!(#output),
+        asyncness: sig.asyncness,
+        unsafety: sig.unsafety,
+        generics,
+    };
+    let mut errors = Errors::default();
+    let s = Struct::new(
+        &input,
+        Target::Fn(Box::new(call)),
+        data,
+        &attrs,
+        &mut errors,
+    );
+
+    let mut builder = expand::derive_struct(&s);
+    builder.extend(errors.to_compile_error());
+    let constructor = crate::typestate::constructor(&s);
+    Ok((builder, constructor))
+}
+
+/// Rewrites a type from the function's signature for use in the builder.
+struct Rewrite<'a> {
+    /// Replaces `Self`.
+    self_ty: Option<&'a Type>,
+    /// Given to elided lifetimes.
+    elided: Option<&'a Lifetime>,
+    /// Whether the type is stored in the builder, which rules out `impl
+    /// Trait` and gives elided lifetimes new names.
+    stored: bool,
+    /// The lifetimes named for those elided in stored types.
+    fresh: Vec<Lifetime>,
+    /// Every lifetime in the stored types, after naming the elided ones.
+    lifetimes: Vec<Lifetime>,
+    errors: Vec<Error>,
+}
+
+impl<'a> Rewrite<'a> {
+    fn new(self_ty: Option<&'a Type>) -> Self {
+        Rewrite {
+            self_ty,
+            elided: None,
+            stored: false,
+            fresh: Vec::new(),
+            lifetimes: Vec::new(),
+            errors: Vec::new(),
+        }
+    }
+
+    /// The lifetime to use for one elided at `span`.
+    fn elided(&mut self, span: Span) -> Option<Lifetime> {
+        if self.stored {
+            let fresh = Lifetime::new(&format!("'__{}", self.fresh.len()), span);
+            self.fresh.push(fresh.clone());
+            Some(fresh)
+        } else {
+            self.elided.cloned()
+        }
+    }
+}
+
+impl VisitMut for Rewrite<'_> {
+    fn visit_type_mut(&mut self, ty: &mut Type) {
+        match ty {
+            Type::Path(path) if path.qself.is_none() && path.path.is_ident("Self") => {
+                if let Some(self_ty) = self.self_ty {
+                    *ty = self_ty.clone();
+                }
+            }
+            Type::ImplTrait(_) if self.stored => self.errors.push(Error::new_spanned(
+                ty,
+                "`impl Trait` parameters cannot be stored in the builder; \
+                 use a named type parameter",
+            )),
+            // Lifetimes elided in these are not the function's.
+            Type::BareFn(_) | Type::TraitObject(_) => {}
+            _ => visit_mut::visit_type_mut(self, ty),
+        }
+    }
+
+    fn visit_type_reference_mut(&mut self, ty: &mut syn::TypeReference) {
+        if ty.lifetime.is_none() {
+            ty.lifetime = self.elided(ty.and_token.span);
+        }
+        visit_mut::visit_type_reference_mut(self, ty);
+    }
+
+    fn visit_lifetime_mut(&mut self, lifetime: &mut Lifetime) {
+        if lifetime.ident == "_" {
+            if let Some(elided) = self.elided(lifetime.span()) {
+                *lifetime = elided;
+            }
+        }
+        if self.stored && !self.lifetimes.contains(lifetime) {
+            self.lifetimes.push(lifetime.clone());
+        }
+    }
+}
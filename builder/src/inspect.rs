@@ -0,0 +1,159 @@
+// Reading and clearing what has been set on a runtime builder: a
+// `get_<field>()` getter and an `unset_<field>()` method per field, which
+// carry the field's `#[deprecated]` like its setters, `reset()`, and the
+// traits requested with `#[builder(derive(...))]`.
+//
+// Getters return what the builder holds, so a field that was never set is
+// `None` even if it has a default. `Debug` is implemented by hand so that
+// such fields show as `<unset>` instead of the `None` of the storage.
+
+use crate::attr::Pattern;
+use crate::expand::Struct;
+use crate::fallible;
+use crate::setter::Setter;
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{parse_quote, Path};
+
+/// The getters and the `unset_<field>()` and `reset()` methods, which go in
+/// the builder's impl block.
+pub fn methods(s: &Struct) -> TokenStream {
+    let core = s.std.core();
+    let vis = s.vis;
+    let mut methods = TokenStream::new();
+
+    for f in &s.fields {
+        let name = &f.ident;
+        let ty = f.setter_ty();
+        let unraw = name.to_string();
+        let unraw = unraw.trim_start_matches("r#");
+        let get = format_ident!("get_{}", unraw);
+        let unset = format_ident!("unset_{}", unraw);
+
+        let doc = format!(
+            "The value given for `{}`, or `None` if it has not been set.",
+            unraw,
+        );
+        let deprecated = &f.attrs.deprecated;
+        methods.extend(quote! {
+            #[doc = #doc]
+            #deprecated
+            #vis fn #get(&self) -> #core::option::Option<&#ty> {
+                #core::option::Option::as_ref(&self.#name)
+            }
+        });
+        let doc = format!("Clears `{}`, as if it had never been set.", unraw);
+        let unset = Setter::new(
+            &unset,
+            TokenStream::new(),
+            TokenStream::new(),
+            Vec::new(),
+            |b| {
+                let invalid = fallible::clear(f, &b);
+                let place = f.place(&b);
+                quote!({
+                    #place = #core::option::Option::None;
+                    #invalid
+                })
+            },
+        );
+        methods.extend(s.method(
+            s.constness(),
+            unset.with_attrs(quote! {
+                #[doc = #doc]
+                #deprecated
+            }),
+        ));
+    }
+
+    if s.has_method("reset") {
+        methods.extend(s.setter(
+            &format_ident!("reset"),
+            "Clears every field, as if none had been set.",
+            TokenStream::new(),
+            TokenStream::new(),
+            Vec::new(),
+            |b| {
+                let fields = s.fields.iter().map(|f| {
+                    let place = f.place(&b);
+                    quote!(#place = #core::option::Option::None;)
+                });
+                let invalid = fallible::reset(s, &b);
+                quote!({ #(#fields)* #invalid })
+            },
+        ));
+    }
+
+    methods
+}
+
+/// The `#[derive(...)]` attribute for the builder struct, and the `Debug`
+/// impl if it was asked for.
+pub fn derives(s: &Struct) -> (TokenStream, TokenStream) {
+    let is = |path: &Path, name: &str| path.segments.last().is_some_and(|last| last.ident == name);
+
+    // Clone is derived already unless the builder is owned.
+    let cloned = s.attrs.pattern != Pattern::Owned;
+    let derives: Vec<&Path> = s
+        .attrs
+        .derives
+        .iter()
+        .filter(|path| !(is(path, "Debug") || cloned && is(path, "Clone")))
+        .collect();
+    let derive = if derives.is_empty() {
+        TokenStream::new()
+    } else {
+        quote!(#[derive(#(#derives),*)])
+    };
+
+    let debug = if s.attrs.derives.iter().any(|path| is(path, "Debug")) {
+        debug(s)
+    } else {
+        TokenStream::new()
+    };
+
+    (derive, debug)
+}
+
+fn debug(s: &Struct) -> TokenStream {
+    let core = s.std.core();
+    let builder = &s.builder;
+    let name = builder.to_string();
+
+    let mut generics = s.generics.clone();
+    if !s.generics.params.is_empty() {
+        let where_clause = generics.make_where_clause();
+        for f in &s.fields {
+            let ty = f.setter_ty();
+            where_clause
+                .predicates
+                .push(parse_quote!(#ty: #core::fmt::Debug));
+        }
+    }
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+    let (_, ty_generics, _) = s.generics.split_for_impl();
+
+    let fields = s.fields.iter().map(|f| {
+        let ident = &f.ident;
+        let field = ident.to_string();
+        let field = field.trim_start_matches("r#");
+        quote! {
+            match &self.#ident {
+                #core::option::Option::Some(value) => debug.field(#field, value),
+                #core::option::Option::None => {
+                    debug.field(#field, &#core::format_args!("<unset>"))
+                }
+            };
+        }
+    });
+
+    quote! {
+        impl #impl_generics #core::fmt::Debug for #builder #ty_generics #where_clause {
+            fn fmt(&self, f: &mut #core::fmt::Formatter) -> #core::fmt::Result {
+                let mut debug = f.debug_struct(#name);
+                #(#fields)*
+                debug.finish()
+            }
+        }
+    }
+}
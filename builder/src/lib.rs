@@ -1,10 +1,35 @@
 extern crate proc_macro;
 
+mod attr;
+mod constant;
+mod doc;
+mod env;
+mod error;
+mod expand;
+mod fallible;
+mod flatten;
+mod func;
+mod inspect;
+mod no_std;
+mod setter;
+mod ty;
+mod typestate;
+
 use proc_macro::TokenStream;
+use syn::{parse_macro_input, DeriveInput, Item};
 
-#[proc_macro_derive(Builder)]
+#[proc_macro_derive(Builder, attributes(builder))]
 pub fn derive(input: TokenStream) -> TokenStream {
-    let _ = input;
+    let input = parse_macro_input!(input as DeriveInput);
+
+    expand::derive(&input)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
+}
+
+#[proc_macro_attribute]
+pub fn builder(args: TokenStream, input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as Item);
 
-    unimplemented!()
+    func::expand(args.into(), input).into()
 }
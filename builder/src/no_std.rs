@@ -0,0 +1,48 @@
+// Paths to the standard library in the generated code.
+//
+// The generated code names everything it uses by an absolute path, starting
+// with the crate chosen here: `std`, or for `#[builder(no_std)]` `core`, and
+// `alloc` for what only `alloc` has such as `String` and `Vec`. The path
+// starts with `::` so that a module of that name in the caller's code is not
+// taken for the crate. The crate deriving a `no_std` builder needs
+// `extern crate alloc;` in its root for the latter. Paths written by the caller, as in field types and default
+// expressions, are left as they are.
+
+use proc_macro2::{Ident, Punct, Spacing, Span, TokenStream};
+use quote::{ToTokens, TokenStreamExt};
+
+/// The crates through which a builder names the standard library.
+#[derive(Clone, Copy)]
+pub struct Std {
+    no_std: bool,
+}
+
+impl Std {
+    pub fn new(no_std: bool) -> Self {
+        Std { no_std }
+    }
+
+    /// `std`, or `core` without the standard library.
+    pub fn core(self) -> Krate {
+        Krate(if self.no_std { "core" } else { "std" })
+    }
+
+    /// `std`, or `alloc` without the standard library, for the modules only
+    /// `alloc` has: `string`, `vec`, `boxed`, `collections`, `borrow`, `rc`,
+    /// `sync` and the `format!` macro.
+    pub fn alloc(self) -> Krate {
+        Krate(if self.no_std { "alloc" } else { "std" })
+    }
+}
+
+/// The start of a path into the standard library, as in `::std`.
+#[derive(Clone, Copy)]
+pub struct Krate(&'static str);
+
+impl ToTokens for Krate {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        tokens.append(Punct::new(':', Spacing::Joint));
+        tokens.append(Punct::new(':', Spacing::Alone));
+        tokens.append(Ident::new(self.0, Span::call_site()));
+    }
+}
@@ -0,0 +1,395 @@
+use crate::attr::Pattern;
+use crate::expand::{Field, Kind, Struct};
+use crate::no_std::Std;
+use crate::ty::{self, Item};
+use crate::{doc, fallible, flatten};
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote, ToTokens};
+use syn::{Ident, Path, Token, Type, TypeParamBound, TypeTraitObject};
+
+/// A builder method that modifies the builder, before it is given the
+/// signature of the struct's pattern.
+pub struct Setter<'a> {
+    pub name: Ident,
+    pub generics: TokenStream,
+    pub args: TokenStream,
+    pub bounds: Vec<TokenStream>,
+    /// Given the builder to modify, as an expression of type `&mut Self` or
+    /// `Self`, returns the statement that does so.
+    pub update: Box<dyn Fn(TokenStream) -> TokenStream + 'a>,
+    /// Docs and other attributes of the method.
+    pub attrs: TokenStream,
+    /// The error type of a setter that can fail, which then returns a
+    /// `Result` and may use `?` in its `update`.
+    pub error: Option<TokenStream>,
+}
+
+impl<'a> Setter<'a> {
+    pub fn new(
+        name: &Ident,
+        generics: TokenStream,
+        args: TokenStream,
+        bounds: Vec<TokenStream>,
+        update: impl Fn(TokenStream) -> TokenStream + 'a,
+    ) -> Self {
+        Setter {
+            name: name.clone(),
+            generics,
+            args,
+            bounds,
+            update: Box::new(update),
+            attrs: TokenStream::new(),
+            error: None,
+        }
+    }
+
+    pub fn with_attrs(mut self, attrs: TokenStream) -> Self {
+        self.attrs = attrs;
+        self
+    }
+}
+
+impl Struct<'_> {
+    /// A builder method in the struct's pattern, documented by `doc`.
+    /// `update` is given the builder to modify, as an expression of type
+    /// `&mut Self` or `Self`.
+    pub fn setter(
+        &self,
+        name: &Ident,
+        doc: &str,
+        generics: TokenStream,
+        args: TokenStream,
+        bounds: Vec<TokenStream>,
+        update: impl Fn(TokenStream) -> TokenStream,
+    ) -> TokenStream {
+        let setter = Setter::new(name, generics, args, bounds, update);
+        self.method(self.constness(), setter.with_attrs(quote!(#[doc = #doc])))
+    }
+
+    pub fn method(&self, constness: Option<Token![const]>, mut setter: Setter) -> TokenStream {
+        let core = self.std.core();
+        if self.attrs.pattern == Pattern::Immutable && !self.generics.params.is_empty() {
+            setter.bounds.push(quote!(Self: #core::clone::Clone));
+        }
+        method(
+            &self.vis.to_token_stream(),
+            self.attrs.pattern,
+            constness,
+            self.std,
+            &setter,
+        )
+    }
+}
+
+/// `setter` as a method of a builder in `pattern`.
+pub fn method(
+    vis: &TokenStream,
+    pattern: Pattern,
+    constness: Option<Token![const]>,
+    std: Std,
+    setter: &Setter,
+) -> TokenStream {
+    let core = std.core();
+    let Setter {
+        name,
+        generics,
+        args,
+        bounds,
+        update,
+        attrs,
+        error,
+    } = setter;
+    let where_clause = if bounds.is_empty() {
+        None
+    } else {
+        Some(quote!(where #(#bounds,)*))
+    };
+    let returns = |ty: TokenStream, value: TokenStream| match error {
+        Some(error) => (
+            quote!(#core::result::Result<#ty, #error>),
+            quote!(#core::result::Result::Ok(#value)),
+        ),
+        None => (ty, value),
+    };
+    match pattern {
+        Pattern::Mutable => {
+            let update = update(quote!(self));
+            let (output, value) = returns(quote!(&mut Self), quote!(self));
+            quote! {
+                #attrs
+                #vis fn #name #generics(&mut self, #args) -> #output #where_clause {
+                    #update;
+                    #value
+                }
+            }
+        }
+        Pattern::Owned => {
+            let update = update(quote!(self));
+            let (output, value) = returns(quote!(Self), quote!(self));
+            quote! {
+                #attrs
+                #vis #constness fn #name #generics(mut self, #args) -> #output #where_clause {
+                    #update;
+                    #value
+                }
+            }
+        }
+        Pattern::Immutable => {
+            let update = update(quote!(__builder));
+            let (output, value) = returns(quote!(Self), quote!(__builder));
+            quote! {
+                #attrs
+                #vis fn #name #generics(&self, #args) -> #output #where_clause {
+                    let mut __builder = #core::clone::Clone::clone(self);
+                    #update;
+                    #value
+                }
+            }
+        }
+    }
+}
+
+impl Field<'_> {
+    /// The wrapper and trait object of a `ty` such as `Box<dyn Trait>`, which
+    /// a setter takes as any `Trait` implementation and wraps if the field
+    /// has `setter(boxed)`.
+    pub fn wrapper<'t>(&self, ty: &'t Type) -> Option<(Path, &'t TypeTraitObject)> {
+        match self.attrs.boxed {
+            Some(true) => ty::trait_object(ty),
+            Some(false) | None => None,
+        }
+    }
+
+    /// The generic parameters, argument type, and converted value for a
+    /// setter whose argument `arg` ends up stored as a `ty`.
+    pub fn setter_arg(&self, arg: &Ident, ty: &Type) -> (TokenStream, TokenStream, TokenStream) {
+        let core = self.std.core();
+        if let Some((wrapper, object)) = self.wrapper(ty) {
+            let bounds = &object.bounds;
+            // `Box<dyn Trait>` is `Box<dyn Trait + 'static>` in a field.
+            let lifetime = if bounds
+                .iter()
+                .any(|bound| matches!(bound, TypeParamBound::Lifetime(_)))
+            {
+                None
+            } else {
+                Some(quote!(+ 'static))
+            };
+            (
+                quote!(<__T: #bounds #lifetime>),
+                quote!(__T),
+                quote!({
+                    let value: #ty = #wrapper::new(#arg);
+                    value
+                }),
+            )
+        } else if self.into {
+            (
+                quote!(<__V: #core::convert::Into<#ty>>),
+                quote!(__V),
+                quote!(#core::convert::Into::into(#arg)),
+            )
+        } else {
+            (TokenStream::new(), quote!(#ty), quote!(#arg))
+        }
+    }
+}
+
+/// Every setter of a field other than the typestate setter of a required
+/// field: the all-at-once setter, and for `each` fields the one-at-a-time
+/// setter along with `extend_<field>` and `clear_<field>`.
+pub fn field_setters(s: &Struct, f: &Field) -> TokenStream {
+    if f.attrs.flatten {
+        return flatten::setters(s, f);
+    }
+    setters(f)
+        .into_iter()
+        .map(|setter| s.method(s.field_constness(f), setter))
+        .collect()
+}
+
+/// The setters of `field_setters`, before they are given a signature.
+pub fn setters<'a>(f: &'a Field) -> Vec<Setter<'a>> {
+    let core = f.std.core();
+    let name = &f.ident;
+    let mut setters = Vec::new();
+
+    if let Kind::Sub(builder, _) = &f.kind {
+        let new = flatten::nested(f.ty, "__builder");
+        setters.extend(aliased(f, |setter| {
+            let new = new.clone();
+            Setter::new(
+                setter,
+                quote!(<__F>),
+                quote!(#name: __F),
+                vec![quote!(__F: #core::ops::FnOnce(&mut #builder))],
+                move |b| quote!(#name(#b.#name.get_or_insert_with(#new))),
+            )
+        }));
+        return setters;
+    }
+
+    if f.has_setter() {
+        let (generics, ty, value) = f.setter_arg(name, f.setter_ty());
+        setters.extend(aliased(f, |setter| {
+            let value = value.clone();
+            Setter::new(
+                setter,
+                generics.clone(),
+                quote!(#name: #ty),
+                Vec::new(),
+                move |b| {
+                    let invalid = fallible::clear(f, &b);
+                    let place = f.place(&b);
+                    quote!({
+                        #place = #core::option::Option::Some(#value);
+                        #invalid
+                    })
+                },
+            )
+        }));
+    }
+
+    if let Some(fallible) = f.attrs.try_into {
+        setters.push(fallible::setter(f, fallible));
+    }
+
+    let item = match f.kind {
+        Kind::Each(item) => item,
+        Kind::Required | Kind::Optional(_) | Kind::Sub(..) => return setters,
+    };
+    let ty = f.ty;
+    let collection =
+        move |b: TokenStream| quote!(#b.#name.get_or_insert_with(#core::default::Default::default));
+
+    if let Some(each) = &f.attrs.each {
+        let attrs = match item {
+            Item::Elem(elem) => doc::taking(f, elem),
+            Item::Entry(..) | Item::Inferred => doc::setter(f),
+        };
+        let (generics, args, bounds, value) = match item {
+            Item::Elem(elem) => {
+                let (generics, ty, value) = f.setter_arg(each, elem);
+                (generics, quote!(#each: #ty), Vec::new(), value)
+            }
+            Item::Entry(key, value) if f.into => (
+                quote!(<__K: #core::convert::Into<#key>, __V: #core::convert::Into<#value>>),
+                quote!(key: __K, value: __V),
+                Vec::new(),
+                quote!((
+                    #core::convert::Into::into(key),
+                    #core::convert::Into::into(value)
+                )),
+            ),
+            Item::Entry(key, value) => (
+                TokenStream::new(),
+                quote!(key: #key, value: #value),
+                Vec::new(),
+                quote!((key, value)),
+            ),
+            Item::Inferred => (
+                quote!(<__I>),
+                quote!(#each: __I),
+                vec![quote!(#ty: #core::iter::Extend<__I>)],
+                quote!(#each),
+            ),
+        };
+        setters.push(
+            Setter::new(each, generics, args, bounds, move |b| {
+                let collection = collection(b);
+                quote! {
+                    #core::iter::Extend::extend(#collection, #core::iter::once(#value))
+                }
+            })
+            .with_attrs(attrs),
+        );
+    }
+
+    let unraw = name.to_string();
+    let unraw = unraw.trim_start_matches("r#");
+    let extend = format_ident!("extend_{}", unraw);
+    let clear = format_ident!("clear_{}", unraw);
+
+    let (generics, bounds, items) = match item {
+        Item::Elem(elem) if f.into => (
+            quote!(<__I, __V>),
+            vec![
+                quote!(__I: #core::iter::IntoIterator<Item = __V>),
+                quote!(__V: #core::convert::Into<#elem>),
+            ],
+            quote! {
+                #core::iter::Iterator::map(
+                    #core::iter::IntoIterator::into_iter(items),
+                    <__V as #core::convert::Into<#elem>>::into,
+                )
+            },
+        ),
+        Item::Elem(elem) => (
+            quote!(<__I>),
+            vec![quote!(__I: #core::iter::IntoIterator<Item = #elem>)],
+            quote!(items),
+        ),
+        Item::Entry(key, value) if f.into => (
+            quote!(<__I, __K, __V>),
+            vec![
+                quote!(__I: #core::iter::IntoIterator<Item = (__K, __V)>),
+                quote!(__K: #core::convert::Into<#key>),
+                quote!(__V: #core::convert::Into<#value>),
+            ],
+            quote! {
+                #core::iter::Iterator::map(
+                    #core::iter::IntoIterator::into_iter(items),
+                    |(key, value)| -> (#key, #value) {
+                        (#core::convert::Into::into(key), #core::convert::Into::into(value))
+                    },
+                )
+            },
+        ),
+        Item::Entry(key, value) => (
+            quote!(<__I>),
+            vec![quote!(__I: #core::iter::IntoIterator<Item = (#key, #value)>)],
+            quote!(items),
+        ),
+        Item::Inferred => (
+            quote!(<__I>),
+            vec![
+                quote!(__I: #core::iter::IntoIterator),
+                quote!(#ty: #core::iter::Extend<<__I as #core::iter::IntoIterator>::Item>),
+            ],
+            quote!(items),
+        ),
+    };
+    setters.push(
+        Setter::new(&extend, generics, quote!(items: __I), bounds, move |b| {
+            let collection = collection(b);
+            quote!(#core::iter::Extend::extend(#collection, #items))
+        })
+        .with_attrs(doc::helper(f, "Adds every item to")),
+    );
+
+    setters.push(
+        Setter::new(
+            &clear,
+            TokenStream::new(),
+            TokenStream::new(),
+            Vec::new(),
+            move |b| quote!(#b.#name = #core::option::Option::Some(#core::default::Default::default())),
+        )
+        .with_attrs(doc::helper(f, "Removes every item from")),
+    );
+
+    setters
+}
+
+/// The field's setter, named after it, followed by the same setter under
+/// each of its `alias` names.
+fn aliased<'a>(f: &'a Field, setter: impl Fn(&Ident) -> Setter<'a>) -> Vec<Setter<'a>> {
+    let mut setters = vec![setter(&f.ident).with_attrs(doc::setter(f))];
+    setters.extend(
+        f.attrs
+            .aliases
+            .iter()
+            .map(|alias| setter(alias).with_attrs(doc::alias(f, &f.ident))),
+    );
+    setters
+}
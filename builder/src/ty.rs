@@ -0,0 +1,106 @@
+use quote::format_ident;
+use syn::{GenericArgument, Path, PathArguments, Type, TypePath, TypeTraitObject};
+
+// Macros only see tokens, not resolved types, so these helpers recognize a
+// wrapper type by the last segment of its path: `Option<T>` and
+// `std::option::Option<T>` both match, a type alias to either does not.
+
+/// Returns `T` if `ty` is written as `Option<T>`.
+pub fn option_inner(ty: &Type) -> Option<&Type> {
+    match generic_args(ty, &["Option"])?.as_slice() {
+        [inner] => Some(inner),
+        _ => None,
+    }
+}
+
+/// What an `each` setter adds to a collection.
+#[derive(Clone, Copy)]
+pub enum Item<'a> {
+    /// `Vec<T>`, `HashSet<T>` and the other std sequences and sets.
+    Elem(&'a Type),
+    /// `HashMap<K, V>` and `BTreeMap<K, V>`, which take a key and a value.
+    Entry(&'a Type, &'a Type),
+    /// Any other `Default + Extend<_>` type. The setter is generic over the
+    /// item and lets the `Extend` impl decide what it accepts.
+    Inferred,
+}
+
+pub fn collection_item(ty: &Type) -> Item<'_> {
+    const SEQUENCES: &[&str] = &[
+        "Vec",
+        "VecDeque",
+        "LinkedList",
+        "BinaryHeap",
+        "HashSet",
+        "BTreeSet",
+    ];
+    const MAPS: &[&str] = &["HashMap", "BTreeMap"];
+
+    if let Some(args) = generic_args(ty, SEQUENCES) {
+        if let Some(elem) = args.first() {
+            return Item::Elem(elem);
+        }
+    }
+    if let Some(args) = generic_args(ty, MAPS) {
+        if let [key, value, ..] = args.as_slice() {
+            return Item::Entry(key, value);
+        }
+    }
+    Item::Inferred
+}
+
+/// Returns the wrapper's path, as in `Box` or `std::sync::Arc`, and the
+/// trait object if `ty` is written as `Box<dyn Trait>`, `Arc<dyn Trait>` or
+/// `Rc<dyn Trait>`.
+pub fn trait_object(ty: &Type) -> Option<(Path, &TypeTraitObject)> {
+    let object = match generic_args(ty, &["Box", "Arc", "Rc"])?.as_slice() {
+        [Type::TraitObject(object)] => object,
+        _ => return None,
+    };
+    let mut path = match ty {
+        Type::Path(TypePath { path, .. }) => path.clone(),
+        _ => return None,
+    };
+    path.segments.last_mut()?.arguments = PathArguments::None;
+    Some((path, object))
+}
+
+/// The builder type derived for `ty`: `path::ConfigBuilder<T>` for
+/// `path::Config<T>`.
+pub fn builder_of(ty: &Type) -> Option<Type> {
+    let mut path = match ty {
+        Type::Path(TypePath { qself: None, path }) => path.clone(),
+        _ => return None,
+    };
+    let segment = path.segments.last_mut()?;
+    segment.ident = format_ident!("{}Builder", segment.ident);
+    Some(Type::Path(TypePath { qself: None, path }))
+}
+
+/// The type arguments of `ty` if its last path segment is one of `wrappers`.
+fn generic_args<'a>(ty: &'a Type, wrappers: &[&str]) -> Option<Vec<&'a Type>> {
+    let path = match ty {
+        Type::Path(TypePath { qself: None, path }) => path,
+        _ => return None,
+    };
+
+    let segment = path.segments.last()?;
+    if !wrappers.iter().any(|wrapper| segment.ident == wrapper) {
+        return None;
+    }
+
+    let args = match &segment.arguments {
+        PathArguments::AngleBracketed(args) => args,
+        _ => return None,
+    };
+
+    Some(
+        args.args
+            .iter()
+            .filter_map(|arg| match arg {
+                GenericArgument::Type(ty) => Some(ty),
+                _ => None,
+            })
+            .collect(),
+    )
+}
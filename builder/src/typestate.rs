@@ -0,0 +1,489 @@
+// Expansion for `#[builder(typestate)]`.
+//
+// Each required field gets a type parameter on the builder which is `()` while
+// the field is unset and `(T,)` once its setter has been called. Setters take
+// the builder by value and return it with the updated state, and `build()` is
+// only implemented for the state in which every required field is `(T,)`. A
+// missing field therefore shows up as "no method named `build`" at compile
+// time, and setting the same required field twice is rejected the same way.
+//
+// Function builders are always typestate builders, with `call()` in place of
+// `build()`.
+//
+// Groups are checked the same way. Their fields also get a state parameter,
+// and `build()` requires a hidden trait generated for the group to hold for
+// the tuple of those states, implemented only for the allowed combinations.
+//
+// The only way for `build()` to fail here is a `build_fn(validate = "...")`
+// hook, so it returns a `Result` and the generated error type only then.
+
+use crate::attr::Rule;
+use crate::expand::{self, Field, Struct, Target};
+use crate::func::Call;
+use crate::{doc, error, fallible, setter};
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote, ToTokens};
+use syn::{parse_quote, GenericParam, Ident};
+
+pub fn expand(s: &Struct) -> TokenStream {
+    let core = s.std.core();
+    let Struct {
+        ident,
+        vis,
+        builder,
+        ..
+    } = s;
+    let (impl_generics, ty_generics, where_clause) = s.generics.split_for_impl();
+    let args = s.generic_args();
+
+    let tracked: Vec<&Field> = s.fields.iter().filter(|f| is_tracked(s, f)).collect();
+    let states: Vec<Ident> = tracked.iter().map(|f| state_ident(&f.ident)).collect();
+
+    // The state parameters go after the struct's own, defaulting to unset so
+    // that `NameBuilder<T>` alone names the fresh builder.
+    let mut decl_generics = s.generics.clone();
+    decl_generics.params.extend(
+        states
+            .iter()
+            .map(|state| -> GenericParam { parse_quote!(#state = ()) }),
+    );
+
+    // Required fields are stored as their state parameter, so the struct's
+    // own parameters may otherwise go unused by the builder.
+    let phantom = s.phantom();
+    let marker = quote!(__marker: #phantom);
+
+    // A method's builder holds on to the receiver until `call()`.
+    let receiver = match &s.target {
+        Target::Fn(call) => call.receiver.as_ref(),
+        Target::Struct | Target::Variant(_) => None,
+    };
+    let (receiver, carry) = match receiver {
+        Some(receiver) => {
+            let ty = &receiver.ty;
+            (
+                Some(quote!(__receiver: #ty,)),
+                Some(quote!(__receiver: self.__receiver,)),
+            )
+        }
+        None => (None, None),
+    };
+
+    let storage = s.fields.iter().map(|f| {
+        let name = &f.ident;
+        if is_tracked(s, f) {
+            let state = state_ident(name);
+            quote!(#name: #state)
+        } else {
+            let ty = f.setter_ty();
+            quote!(#name: #core::option::Option<#ty>)
+        }
+    });
+
+    let tracked_setters = tracked.iter().enumerate().map(|(i, f)| {
+        let name = &f.ident;
+        let ty = f.setter_ty();
+        let free = states
+            .iter()
+            .enumerate()
+            .filter(|(j, _)| *j != i)
+            .map(|(_, state)| state);
+        let before =
+            states.iter().enumerate().map(
+                |(j, state)| {
+                    if j == i {
+                        quote!(())
+                    } else {
+                        quote!(#state)
+                    }
+                },
+            );
+        let after: Vec<TokenStream> = states
+            .iter()
+            .enumerate()
+            .map(|(j, state)| {
+                if j == i {
+                    quote!((#ty,))
+                } else {
+                    quote!(#state)
+                }
+            })
+            .collect();
+        let rest: Vec<&Ident> = s
+            .fields
+            .iter()
+            .map(|f| &f.ident)
+            .filter(|other| *other != name)
+            .collect();
+        let impl_generics = with_states(s, free);
+        let (generics, arg_ty, value) = f.setter_arg(name, ty);
+        let aliases = &f.attrs.aliases;
+        let setters = std::iter::once((name, doc::setter(f)))
+            .chain(aliases.iter().map(|alias| (alias, doc::alias(f, name))))
+            .map(|(setter, attrs)| {
+                quote! {
+                    #attrs
+                    #vis fn #setter #generics(self, #name: #arg_ty) -> #builder<#(#args,)* #(#after),*> {
+                        #builder {
+                            #name: (#value,),
+                            #(#rest: self.#rest,)*
+                            #carry
+                            __marker: #core::marker::PhantomData,
+                        }
+                    }
+                }
+            });
+        // Deferred conversions are rejected in typestate mode.
+        let try_setter = f.attrs.try_into.map(|fallible| {
+            let try_name = fallible::ident(f);
+            let attrs = doc::try_setter(f, fallible);
+            quote! {
+                #attrs
+                #vis fn #try_name<__V>(
+                    self,
+                    #name: __V,
+                ) -> #core::result::Result<
+                    #builder<#(#args,)* #(#after),*>,
+                    <__V as #core::convert::TryInto<#ty>>::Error,
+                >
+                where
+                    __V: #core::convert::TryInto<#ty>,
+                {
+                    let #name: #ty = #core::convert::TryInto::try_into(#name)?;
+                    #core::result::Result::Ok(#builder {
+                        #name: (#name,),
+                        #(#rest: self.#rest,)*
+                        #carry
+                        __marker: #core::marker::PhantomData,
+                    })
+                }
+            }
+        });
+        quote! {
+            impl #impl_generics #builder<#(#args,)* #(#before),*> #where_clause {
+                #(#setters)*
+                #try_setter
+            }
+        }
+    });
+
+    // The struct's pattern is always owned in typestate mode, which is what
+    // these setters need.
+    let other_setters = s
+        .fields
+        .iter()
+        .filter(|f| !is_tracked(s, f))
+        .map(|f| setter::field_setters(s, f));
+
+    // Members of a group may be in either state when building, as long as the
+    // group's trait below holds for them.
+    let field_trait = format_ident!("__{}Field", builder);
+    let complete: Vec<TokenStream> = tracked
+        .iter()
+        .zip(&states)
+        .map(|(f, state)| {
+            if f.is_required() {
+                let ty = f.ty;
+                quote!((#ty,))
+            } else {
+                quote!(#state)
+            }
+        })
+        .collect();
+    let members: Vec<&Ident> = tracked
+        .iter()
+        .zip(&states)
+        .filter(|(f, _)| !f.is_required())
+        .map(|(_, state)| state)
+        .collect();
+    let build_generics = with_states(s, members.iter().copied());
+    let mut build_bounds: Vec<TokenStream> = tracked
+        .iter()
+        .zip(&states)
+        .filter(|(f, _)| !f.is_required())
+        .map(|(f, state)| {
+            let ty = f.setter_ty();
+            quote!(#state: #field_trait<#ty>)
+        })
+        .collect();
+    let groups = s.attrs.groups.iter().enumerate().map(|(i, group)| {
+        let group_trait = format_ident!("__{}Group{}", builder, i);
+        let group_states: Vec<Ident> = group.fields.iter().map(state_ident).collect();
+        build_bounds.push(quote!((#(#group_states,)*): #group_trait));
+
+        let n = group.fields.len();
+        let set = |j: usize| format_ident!("__S{}", j);
+        let impls: Vec<Vec<usize>> = match group.rule {
+            Rule::ExactlyOne => (0..n).map(|j| vec![j]).collect(),
+            Rule::RequiresAll => vec![Vec::new(), (0..n).collect()],
+        };
+        let impls = impls.into_iter().map(|which| {
+            let params = which.iter().map(|&j| set(j));
+            let tuple = (0..n).map(|j| {
+                if which.contains(&j) {
+                    let param = set(j);
+                    quote!((#param,))
+                } else {
+                    quote!(())
+                }
+            });
+            quote!(impl<#(#params),*> #group_trait for (#(#tuple,)*) {})
+        });
+
+        let names = group
+            .fields
+            .iter()
+            .map(|field| format!("`{}`", field))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let message = match group.rule {
+            Rule::ExactlyOne => format!(
+                "`{}` needs exactly one of {} to be set (group \"{}\")",
+                builder,
+                names,
+                group.name.value(),
+            ),
+            Rule::RequiresAll => format!(
+                "`{}` needs either all or none of {} to be set (group \"{}\")",
+                builder,
+                names,
+                group.name.value(),
+            ),
+        };
+        quote! {
+            #[doc(hidden)]
+            #[diagnostic::on_unimplemented(message = #message)]
+            #vis trait #group_trait {}
+            #(#impls)*
+        }
+    });
+    let groups: Vec<TokenStream> = groups.collect();
+    let state_traits = if members.is_empty() {
+        None
+    } else {
+        Some(quote! {
+            #[doc(hidden)]
+            #vis trait #field_trait<__T> {
+                fn take(self) -> #core::option::Option<__T>;
+            }
+
+            impl<__T> #field_trait<__T> for () {
+                fn take(self) -> #core::option::Option<__T> {
+                    #core::option::Option::None
+                }
+            }
+
+            impl<__T> #field_trait<__T> for (__T,) {
+                fn take(self) -> #core::option::Option<__T> {
+                    #core::option::Option::Some(self.0)
+                }
+            }
+
+            #(#groups)*
+        })
+    };
+
+    let bindings = s.fields.iter().map(|f| {
+        let name = &f.ident;
+        if f.is_required() {
+            quote!(let #name = self.#name.0;)
+        } else if is_tracked(s, f) {
+            let ty = f.setter_ty();
+            let value = f.value(quote!(<_ as #field_trait<#ty>>::take(self.#name)));
+            quote!(let #name = #value;)
+        } else {
+            let value = f.value(quote!(self.#name));
+            quote!(let #name = #value;)
+        }
+    });
+
+    let construct = s.construct();
+
+    let from = s.fields.iter().map(|f| {
+        let name = &f.ident;
+        let member = &f.member;
+        if f.is_required() {
+            quote!(#name: (value.#member,))
+        } else {
+            let stored = f.stored(quote!(value.#member));
+            quote!(#name: #stored)
+        }
+    });
+
+    let build_fn = s.build_fn();
+    let (qualifiers, output) = match &s.target {
+        Target::Struct | Target::Variant(_) => (None, quote!(#ident #ty_generics)),
+        Target::Fn(call) => {
+            let Call {
+                asyncness,
+                unsafety,
+                output,
+                ..
+            } = &**call;
+            (Some(quote!(#asyncness #unsafety)), output.clone())
+        }
+    };
+
+    let build_doc = doc::build_fn(s);
+    let build = if s.attrs.validate.is_some() {
+        let validate = error::validate(s, quote!(&self));
+        let error_ty = error::return_ty(s);
+        let error = error::expand(s);
+        quote! {
+            impl #build_generics #builder<#(#args,)* #(#complete),*> #where_clause {
+                #build_doc
+                #[allow(deprecated)]
+                #vis #qualifiers fn #build_fn(self) -> #core::result::Result<#output, #error_ty>
+                where
+                    #(#build_bounds,)*
+                {
+                    #validate
+                    #(#bindings)*
+                    #core::result::Result::Ok(#construct)
+                }
+            }
+
+            #error
+        }
+    } else {
+        quote! {
+            impl #build_generics #builder<#(#args,)* #(#complete),*> #where_clause {
+                #build_doc
+                #[allow(deprecated)]
+                #vis #qualifiers fn #build_fn(self) -> #output
+                where
+                    #(#build_bounds,)*
+                {
+                    #(#bindings)*
+                    #construct
+                }
+            }
+        }
+    };
+
+    // Enum variants have no conversion from the enum, which may hold any of
+    // them, and the state of a group's fields depends on the value.
+    let from = match s.target {
+        Target::Variant(_) | Target::Fn(_) => None,
+        Target::Struct if !members.is_empty() => None,
+        Target::Struct => Some(quote! {
+            impl #impl_generics #core::convert::From<#ident #ty_generics>
+                for #builder<#(#args,)* #(#complete),*>
+            #where_clause
+            {
+                #[allow(deprecated)]
+                fn from(value: #ident #ty_generics) -> Self {
+                    #builder {
+                        #(#from,)*
+                        __marker: #core::marker::PhantomData,
+                    }
+                }
+            }
+        }),
+    };
+
+    // A function's builder is returned by a function next to it instead.
+    let new = match s.target {
+        Target::Struct | Target::Variant(_) => {
+            let constructor = constructor(s);
+            Some(quote! {
+                impl #impl_generics #ident #ty_generics #where_clause {
+                    #constructor
+                }
+            })
+        }
+        Target::Fn(_) => None,
+    };
+
+    let all_states = with_states(s, states.iter());
+    let builder_doc = doc::builder(s);
+
+    quote! {
+        #builder_doc
+        #vis struct #builder #decl_generics #where_clause {
+            #(#storage,)*
+            #receiver
+            #marker,
+        }
+
+        #new
+
+        #(#tracked_setters)*
+
+        impl #all_states #builder<#(#args,)* #(#states),*> #where_clause {
+            #(#other_setters)*
+        }
+
+        #from
+
+        #build
+
+        #state_traits
+    }
+}
+
+/// The function returning a builder with nothing set: `builder()` and the
+/// like, or `connect_builder()` for a function, which also takes the receiver
+/// of a method.
+pub fn constructor(s: &Struct) -> TokenStream {
+    let core = s.std.core();
+    let Struct { vis, builder, .. } = s;
+    let (_, ty_generics, _) = s.generics.split_for_impl();
+    let builder_fn = s.builder_fn();
+
+    let init = s.fields.iter().map(|f| {
+        let name = &f.ident;
+        if is_tracked(s, f) {
+            quote!(#name: ())
+        } else {
+            quote!(#name: #core::option::Option::None)
+        }
+    });
+
+    let (generics, receiver, carry) = match &s.target {
+        Target::Fn(call) => match &call.receiver {
+            Some(receiver) => (
+                Some(&call.generics),
+                Some(&receiver.arg),
+                Some(quote!(__receiver: self,)),
+            ),
+            None => (Some(&call.generics), None, None),
+        },
+        Target::Struct | Target::Variant(_) => (None, None, None),
+    };
+    let where_clause = generics.and_then(|generics| generics.where_clause.as_ref());
+    let doc = doc::builder_fn(s);
+
+    quote! {
+        #doc
+        #vis fn #builder_fn #generics(#receiver) -> #builder #ty_generics #where_clause {
+            #builder {
+                #(#init,)*
+                #carry
+                __marker: #core::marker::PhantomData,
+            }
+        }
+    }
+}
+
+/// Whether the field's state is part of the builder's type: required fields,
+/// and fields of a group whose rule is checked at compile time.
+fn is_tracked(s: &Struct, f: &Field) -> bool {
+    f.is_required() || s.in_group(f)
+}
+
+/// The struct's generic parameters followed by `states`, for the header of an
+/// impl that is generic over those states.
+fn with_states<'a>(s: &Struct, states: impl Iterator<Item = &'a Ident>) -> TokenStream {
+    let mut generics = s.generics.clone();
+    generics
+        .params
+        .extend(states.map(|state| -> GenericParam { parse_quote!(#state) }));
+    let (impl_generics, _, _) = generics.split_for_impl();
+    impl_generics.to_token_stream()
+}
+
+/// `current_dir` becomes `__CurrentDir`.
+fn state_ident(field: &Ident) -> Ident {
+    format_ident!("__{}", expand::camel_case(field))
+}
@@ -0,0 +1,40 @@
+// With #[builder(typestate)] the builder tracks which required fields have
+// been set in its type parameters, so `build()` is only callable once all of
+// them are present and it returns the struct directly instead of a Result.
+//
+// Optional fields and `each` fields are not part of the state and may be set
+// in any order, any number of times.
+
+use derive_builder::Builder;
+
+#[derive(Builder)]
+#[builder(typestate)]
+pub struct Command {
+    executable: String,
+    #[builder(each = "arg")]
+    args: Vec<String>,
+    env: Vec<String>,
+    current_dir: Option<String>,
+}
+
+fn main() {
+    let command: Command = Command::builder()
+        .arg("build".to_owned())
+        .env(vec![])
+        .executable("cargo".to_owned())
+        .arg("--release".to_owned())
+        .build();
+
+    assert_eq!(command.executable, "cargo");
+    assert_eq!(command.args, vec!["build", "--release"]);
+    assert!(command.current_dir.is_none());
+
+    let command = Command::builder()
+        .current_dir("..".to_owned())
+        .executable("cargo".to_owned())
+        .env(vec!["RUST_LOG=info".to_owned()])
+        .build();
+
+    assert!(command.args.is_empty());
+    assert_eq!(command.current_dir.as_deref(), Some(".."));
+}
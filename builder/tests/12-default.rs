@@ -0,0 +1,61 @@
+// Fields with #[builder(default)] fall back to Default::default() when their
+// setter was never called, and fields with #[builder(default = "...")] fall
+// back to the given expression.
+//
+// Fields are resolved in declaration order and each one is bound to a local
+// variable of the same name, so a default expression can use the final value
+// of any field declared above it.
+
+use derive_builder::Builder;
+use std::path::{Path, PathBuf};
+
+#[derive(Builder)]
+pub struct Command {
+    executable: PathBuf,
+    #[builder(default)]
+    args: Vec<String>,
+    #[builder(default = "vec![\"RUST_LOG=info\".to_owned()]")]
+    env: Vec<String>,
+    #[builder(default = "executable.parent().map(Path::to_path_buf).unwrap_or_default()")]
+    current_dir: PathBuf,
+    #[builder(default = "Some(args.len())")]
+    jobs: Option<usize>,
+}
+
+#[derive(Builder)]
+#[builder(typestate)]
+pub struct Job {
+    name: String,
+    #[builder(default = "name.len() as u32 * 10")]
+    priority: u32,
+}
+
+fn main() {
+    let command = Command::builder()
+        .executable(PathBuf::from("/usr/bin/cargo"))
+        .build()
+        .unwrap();
+
+    assert!(command.args.is_empty());
+    assert_eq!(command.env, vec!["RUST_LOG=info"]);
+    assert_eq!(command.current_dir, Path::new("/usr/bin"));
+    assert_eq!(command.jobs, Some(0));
+
+    let command = Command::builder()
+        .executable(PathBuf::from("cargo"))
+        .args(vec!["build".to_owned()])
+        .current_dir(PathBuf::from(".."))
+        .jobs(4)
+        .build()
+        .unwrap();
+
+    assert_eq!(command.args, vec!["build"]);
+    assert_eq!(command.current_dir, Path::new(".."));
+    assert_eq!(command.jobs, Some(4));
+
+    let job = Job::builder().name("test".to_owned()).build();
+    assert_eq!(job.priority, 40);
+
+    let job = Job::builder().priority(1).name("test".to_owned()).build();
+    assert_eq!(job.priority, 1);
+}
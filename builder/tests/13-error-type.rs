@@ -0,0 +1,68 @@
+// build() returns a generated CommandBuilderError enum rather than a boxed
+// trait object, so callers can match on which field was missing.
+//
+// With #[builder(error = "...")] the builder instead returns the caller's own
+// error type, converting into it through a From<CommandBuilderError> impl.
+//
+// Besides UninitializedField and ValidationError, the enum only has the
+// variants its builder can return, such as MissingFields when more than one
+// field is required. It is #[non_exhaustive], so that another crate matching
+// on it does not break when a change to the struct adds a variant.
+
+use derive_builder::Builder;
+use std::fmt::Display;
+
+#[derive(Builder)]
+pub struct Command {
+    executable: String,
+    #[builder(each = "arg")]
+    args: Vec<String>,
+    current_dir: Option<String>,
+}
+
+mod app {
+    #[derive(Debug)]
+    pub enum Error {
+        Config(String),
+    }
+
+    impl From<super::ServerBuilderError> for Error {
+        fn from(err: super::ServerBuilderError) -> Self {
+            Error::Config(err.to_string())
+        }
+    }
+}
+
+#[derive(Builder)]
+#[builder(error = "app::Error")]
+pub struct Server {
+    host: String,
+    port: u16,
+}
+
+fn check_error_trait<E: std::error::Error + Display>(_: &E) {}
+
+fn main() {
+    let err = Command::builder().arg("build".to_owned()).build().err().unwrap();
+    check_error_trait(&err);
+    match err {
+        CommandBuilderError::UninitializedField(field) => assert_eq!(field, "executable"),
+        CommandBuilderError::ValidationError(_) => unreachable!(),
+    }
+    assert_eq!(err.to_string(), "`executable` must be initialized");
+    assert_eq!(
+        CommandBuilderError::ValidationError("no args".to_owned()).to_string(),
+        "no args",
+    );
+
+    let err: app::Error = Server::builder().host("localhost".to_owned()).build().err().unwrap();
+    match err {
+        app::Error::Config(message) => assert_eq!(message, "`port` must be initialized"),
+    }
+
+    let result: Result<Server, app::Error> = Server::builder()
+        .host("localhost".to_owned())
+        .port(8080)
+        .build();
+    assert_eq!(result.unwrap().port, 8080);
+}
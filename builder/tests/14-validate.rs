@@ -0,0 +1,76 @@
+// #[builder(build_fn(validate = "..."))] names a function that build() calls
+// with a reference to the builder once every required field is known to be
+// present. An Err(String) from it is returned from build() as a
+// ValidationError.
+
+use derive_builder::Builder;
+
+#[derive(Builder)]
+#[builder(build_fn(validate = "checks::command"))]
+pub struct Command {
+    executable: String,
+    #[builder(each = "arg")]
+    args: Vec<String>,
+    current_dir: Option<String>,
+}
+
+mod checks {
+    pub fn command(builder: &super::CommandBuilder) -> Result<(), String> {
+        let cargo = builder.executable.as_deref() == Some("cargo");
+        let no_args = builder.args.as_ref().map_or(true, Vec::is_empty);
+        if cargo && no_args {
+            return Err("args must not be empty when executable is cargo".to_owned());
+        }
+        Ok(())
+    }
+
+    pub fn job<S>(builder: &super::JobBuilder<S>) -> Result<(), String> {
+        match builder.retries {
+            Some(retries) if retries > 3 => Err(format!("too many retries: {}", retries)),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[derive(Builder)]
+#[builder(typestate, build_fn(validate = "checks::job"))]
+pub struct Job {
+    name: String,
+    retries: Option<u32>,
+}
+
+fn main() {
+    let err = Command::builder()
+        .executable("cargo".to_owned())
+        .build()
+        .err()
+        .unwrap();
+    match err {
+        CommandBuilderError::ValidationError(message) => {
+            assert_eq!(message, "args must not be empty when executable is cargo");
+        }
+        CommandBuilderError::UninitializedField(_) => unreachable!(),
+    }
+
+    // Required fields are checked before the hook runs.
+    let err = Command::builder().build().err().unwrap();
+    assert_eq!(err.to_string(), "`executable` must be initialized");
+
+    let command = Command::builder()
+        .executable("cargo".to_owned())
+        .arg("build".to_owned())
+        .build()
+        .unwrap();
+    assert_eq!(command.args, vec!["build"]);
+
+    let job = Job::builder().name("test".to_owned()).build().unwrap();
+    assert_eq!(job.name, "test");
+
+    let err = Job::builder()
+        .name("test".to_owned())
+        .retries(5)
+        .build()
+        .err()
+        .unwrap();
+    assert_eq!(err.to_string(), "too many retries: 5");
+}
@@ -0,0 +1,62 @@
+// #[builder(setter(into))] makes setters generic over anything that converts
+// into the field type. On the struct it applies to every field, and a field
+// can opt back out with setter(into = false).
+//
+// Option<T> fields take T by default. With setter(strip_option = false) the
+// setter takes the whole Option<T> instead, and an unset field is None.
+
+use derive_builder::Builder;
+use std::path::PathBuf;
+
+#[derive(Builder)]
+pub struct Command {
+    #[builder(setter(into))]
+    executable: String,
+    #[builder(each = "arg", setter(into))]
+    args: Vec<String>,
+    #[builder(setter(into))]
+    current_dir: Option<PathBuf>,
+    #[builder(setter(strip_option = false))]
+    jobs: Option<usize>,
+}
+
+#[derive(Builder)]
+#[builder(typestate, setter(into))]
+pub struct Job {
+    name: String,
+    #[builder(each = "tag")]
+    tags: Vec<String>,
+    #[builder(setter(into = false))]
+    retries: u32,
+}
+
+fn main() {
+    let command = Command::builder()
+        .executable("cargo")
+        .arg("build")
+        .arg(String::from("--release"))
+        .current_dir("..")
+        .jobs(None)
+        .build()
+        .unwrap();
+
+    assert_eq!(command.executable, "cargo");
+    assert_eq!(command.args, vec!["build", "--release"]);
+    assert_eq!(command.current_dir, Some(PathBuf::from("..")));
+    assert_eq!(command.jobs, None);
+
+    let command = Command::builder()
+        .executable("cargo")
+        .jobs(Some(4))
+        .build()
+        .unwrap();
+    assert_eq!(command.jobs, Some(4));
+
+    let command = Command::builder().executable("cargo").build().unwrap();
+    assert_eq!(command.jobs, None);
+
+    let job = Job::builder().name("test").tag("ci").retries(3).build();
+    assert_eq!(job.name, "test");
+    assert_eq!(job.tags, vec!["ci"]);
+    assert_eq!(job.retries, 3);
+}
@@ -0,0 +1,60 @@
+// #[builder(pattern = "...")] selects how setters and build() take the
+// builder.
+//
+//   - "mutable" (the default): `&mut self -> &mut Self` setters and a
+//     `build(&self)` that clones the fields.
+//   - "owned": `self -> Self` setters and a consuming `build(self)` that moves
+//     the fields, so they need not implement Clone.
+//   - "immutable": `&self -> Self` setters that return an updated copy, which
+//     makes it cheap to derive several values from a shared base builder.
+//
+// Mutable and immutable builders implement Clone.
+
+use derive_builder::Builder;
+
+pub struct Handle(u32);
+
+#[derive(Builder)]
+#[builder(pattern = "owned")]
+pub struct Process {
+    executable: String,
+    #[builder(each = "arg")]
+    args: Vec<String>,
+    stdin: Handle,
+    current_dir: Option<String>,
+}
+
+fn process_builder(executable: &str) -> ProcessBuilder {
+    Process::builder().executable(executable.to_owned())
+}
+
+#[derive(Builder)]
+#[builder(pattern = "immutable")]
+pub struct Command {
+    executable: String,
+    #[builder(each = "arg")]
+    args: Vec<String>,
+}
+
+fn main() {
+    let process = process_builder("cargo")
+        .arg("build".to_owned())
+        .stdin(Handle(0))
+        .build()
+        .unwrap();
+    assert_eq!(process.executable, "cargo");
+    assert_eq!(process.args, vec!["build"]);
+    assert_eq!(process.stdin.0, 0);
+    assert!(process.current_dir.is_none());
+
+    let err = process_builder("cargo").build().err().unwrap();
+    assert_eq!(err.to_string(), "`stdin` must be initialized");
+
+    let base = Command::builder().executable("cargo".to_owned());
+    let build = base.arg("build".to_owned());
+    let test = base.arg("test".to_owned()).arg("--release".to_owned());
+
+    assert_eq!(base.build().unwrap().args, Vec::<String>::new());
+    assert_eq!(build.build().unwrap().args, vec!["build"]);
+    assert_eq!(test.clone().build().unwrap().args, vec!["test", "--release"]);
+}
@@ -0,0 +1,82 @@
+// #[builder(each = "...")] works for any field type that implements Default
+// and Extend. For the std sequences and sets the one-at-a-time setter takes an
+// element, for HashMap and BTreeMap it takes a key and a value, and for any
+// other type it is generic over whatever the type's Extend impl accepts.
+//
+// Every `each` field also gets `extend_<field>`, which adds all items of an
+// iterator, and `clear_<field>`, which empties the collection.
+
+use derive_builder::Builder;
+use std::collections::{BTreeSet, HashMap};
+
+#[derive(Clone, Default, Debug, PartialEq)]
+pub struct CommaList(String);
+
+impl<'a> Extend<&'a str> for CommaList {
+    fn extend<I: IntoIterator<Item = &'a str>>(&mut self, iter: I) {
+        for item in iter {
+            if !self.0.is_empty() {
+                self.0.push(',');
+            }
+            self.0.push_str(item);
+        }
+    }
+}
+
+#[derive(Builder)]
+pub struct Command {
+    executable: String,
+    #[builder(each = "arg")]
+    args: Vec<String>,
+    #[builder(each = "env", setter(into))]
+    env: HashMap<String, String>,
+    #[builder(each = "feature")]
+    features: BTreeSet<&'static str>,
+    #[builder(each = "target")]
+    targets: CommaList,
+}
+
+fn main() {
+    let command = Command::builder()
+        .executable("cargo".to_owned())
+        .env("RUST_LOG", "info")
+        .env("RUST_BACKTRACE", "1")
+        .feature("serde")
+        .feature("std")
+        .feature("serde")
+        .target("x86_64")
+        .target("aarch64")
+        .build()
+        .unwrap();
+
+    assert_eq!(command.env.len(), 2);
+    assert_eq!(command.env["RUST_LOG"], "info");
+    assert_eq!(command.features.into_iter().collect::<Vec<_>>(), ["serde", "std"]);
+    assert_eq!(command.targets, CommaList("x86_64,aarch64".to_owned()));
+
+    let command = Command::builder()
+        .executable("cargo".to_owned())
+        .arg("build".to_owned())
+        .extend_args(vec!["--release".to_owned(), "--locked".to_owned()])
+        .extend_env(vec![("CC", "clang")])
+        .extend_targets(vec!["wasm32"])
+        .build()
+        .unwrap();
+
+    assert_eq!(command.args, ["build", "--release", "--locked"]);
+    assert_eq!(command.env["CC"], "clang");
+    assert_eq!(command.targets, CommaList("wasm32".to_owned()));
+
+    let command = Command::builder()
+        .executable("cargo".to_owned())
+        .arg("build".to_owned())
+        .feature("std")
+        .clear_args()
+        .clear_features()
+        .arg("test".to_owned())
+        .build()
+        .unwrap();
+
+    assert_eq!(command.args, ["test"]);
+    assert!(command.features.is_empty());
+}
@@ -0,0 +1,67 @@
+// A builder can start out from an existing value, with every field already
+// set, through `From<Command> for CommandBuilder`. Builders in the mutable and
+// immutable patterns, which clone their fields anyway, also get a
+// `Command::to_builder(&self)` that leaves the original in place.
+//
+// Collections built with `each` keep the elements they already hold, and the
+// one-at-a-time setter appends to them.
+
+use derive_builder::Builder;
+
+#[derive(Builder)]
+pub struct Command {
+    executable: String,
+    #[builder(each = "arg")]
+    args: Vec<String>,
+    current_dir: Option<String>,
+}
+
+#[derive(Builder)]
+#[builder(pattern = "owned")]
+pub struct Process {
+    executable: String,
+    stdin: Option<Box<dyn std::io::Read>>,
+}
+
+#[derive(Builder)]
+#[builder(typestate)]
+pub struct Job {
+    name: String,
+    retries: Option<u32>,
+}
+
+fn main() {
+    let command = Command::builder()
+        .executable("cargo".to_owned())
+        .arg("build".to_owned())
+        .current_dir("..".to_owned())
+        .build()
+        .unwrap();
+
+    let release = command.to_builder().arg("--release".to_owned()).build().unwrap();
+    assert_eq!(release.args, ["build", "--release"]);
+    assert_eq!(release.current_dir.as_deref(), Some(".."));
+    assert_eq!(command.args, ["build"]);
+
+    let test = CommandBuilder::from(command)
+        .executable("cross".to_owned())
+        .build()
+        .unwrap();
+    assert_eq!(test.executable, "cross");
+    assert_eq!(test.args, ["build"]);
+
+    let process = Process::builder()
+        .executable("cat".to_owned())
+        .build()
+        .unwrap();
+    let process = ProcessBuilder::from(process)
+        .stdin(Box::new(std::io::empty()))
+        .build()
+        .unwrap();
+    assert!(process.stdin.is_some());
+
+    let job = Job::builder().name("test".to_owned()).build();
+    let job = JobBuilder::from(job).retries(3).build();
+    assert_eq!(job.name, "test");
+    assert_eq!(job.retries, Some(3));
+}
@@ -0,0 +1,71 @@
+// Layered configuration: each layer fills in some fields of a builder, and
+// `merge` overrides this builder's fields with the ones that are set in the
+// other builder. Fields marked #[builder(merge = "append")], which must be
+// `each` fields, concatenate the two collections instead. The nested
+// builders of `sub_builder` fields are merged in turn.
+//
+// merge follows the builder pattern like any setter, so the layers can be
+// chained.
+
+use derive_builder::Builder;
+
+#[derive(Builder)]
+pub struct Command {
+    executable: String,
+    #[builder(each = "arg")]
+    args: Vec<String>,
+    #[builder(merge = "append", each = "env")]
+    env: Vec<String>,
+    current_dir: Option<String>,
+}
+
+#[derive(Builder)]
+pub struct Job {
+    #[builder(sub_builder)]
+    command: Command,
+    retries: u32,
+}
+
+fn main() {
+    let mut defaults = Command::builder();
+    defaults
+        .executable("cargo".to_owned())
+        .arg("build".to_owned())
+        .env("RUST_LOG=warn".to_owned())
+        .current_dir(".".to_owned());
+
+    let mut file = Command::builder();
+    file.arg("test".to_owned()).env("RUST_BACKTRACE=1".to_owned());
+
+    let mut cli = Command::builder();
+    cli.current_dir("..".to_owned()).env("RUST_LOG=debug".to_owned());
+
+    let command = defaults.merge(file).merge(cli).build().unwrap();
+
+    assert_eq!(command.executable, "cargo");
+    assert_eq!(command.args, ["test"]);
+    assert_eq!(command.env, ["RUST_LOG=warn", "RUST_BACKTRACE=1", "RUST_LOG=debug"]);
+    assert_eq!(command.current_dir.as_deref(), Some(".."));
+
+    // Unset fields in the other builder leave this one's alone.
+    let command = Command::builder()
+        .executable("cargo".to_owned())
+        .merge(Command::builder())
+        .build()
+        .unwrap();
+    assert_eq!(command.executable, "cargo");
+
+    // A nested builder keeps the fields that the other one does not set.
+    let mut defaults = Job::builder();
+    defaults.retries(1).command(|command| {
+        command.executable("cargo".to_owned()).arg("build".to_owned());
+    });
+    let mut cli = Job::builder();
+    cli.command(|command| {
+        command.arg("test".to_owned());
+    });
+    let job = defaults.merge(cli).build().unwrap();
+    assert_eq!(job.command.executable, "cargo");
+    assert_eq!(job.command.args, ["test"]);
+    assert_eq!(job.retries, 1);
+}
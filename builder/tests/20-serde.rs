@@ -0,0 +1,47 @@
+// With the `serde` feature enabled, #[builder(serde)] derives Serialize and
+// Deserialize for the builder. Keys missing from the input leave the field
+// unset, so a partial config can be loaded and then completed in code, and
+// build() still reports whatever is still missing. Unset fields are left out
+// when serializing.
+//
+// #[builder(rename = "...")] changes a field's name in the serialized form.
+//
+// The derives name serde by its absolute path, so a local module of the same
+// name does not get in the way.
+
+use derive_builder::Builder;
+
+#[allow(dead_code)]
+mod serde {}
+
+#[derive(Builder)]
+#[builder(serde)]
+pub struct Command {
+    executable: String,
+    #[builder(each = "arg")]
+    args: Vec<String>,
+    #[builder(rename = "cwd")]
+    current_dir: Option<String>,
+    jobs: u32,
+}
+
+fn main() {
+    let mut builder: CommandBuilder =
+        serde_json::from_str(r#"{ "executable": "cargo", "cwd": ".." }"#).unwrap();
+
+    let err = builder.build().err().unwrap();
+    assert_eq!(err.to_string(), "`jobs` must be initialized");
+
+    let command = builder.arg("build".to_owned()).jobs(4).build().unwrap();
+    assert_eq!(command.executable, "cargo");
+    assert_eq!(command.args, ["build"]);
+    assert_eq!(command.current_dir.as_deref(), Some(".."));
+    assert_eq!(command.jobs, 4);
+
+    let mut partial = Command::builder();
+    partial.current_dir("/tmp".to_owned()).jobs(1);
+    assert_eq!(
+        serde_json::to_string(&partial).unwrap(),
+        r#"{"cwd":"/tmp","jobs":1}"#,
+    );
+}
@@ -0,0 +1,59 @@
+// Fields with #[builder(env = "VAR")] can be filled in from environment
+// variables. `CommandBuilder::from_env()` returns a builder with each of those
+// fields parsed from its variable with FromStr, leaving the field unset when
+// the variable is absent. `from_env_with` takes the lookup as a closure.
+//
+// A value that fails to parse is reported as the InvalidEnv variant of the
+// builder error, naming both the field and the variable.
+
+use derive_builder::Builder;
+use std::collections::HashMap;
+
+#[derive(Builder, Debug)]
+pub struct Command {
+    #[builder(env = "BUILDER_TEST_EXECUTABLE")]
+    executable: String,
+    #[builder(env = "BUILDER_TEST_JOBS")]
+    jobs: u32,
+    #[builder(env = "BUILDER_TEST_TARGET_DIR")]
+    target_dir: Option<String>,
+    #[builder(each = "arg")]
+    args: Vec<String>,
+}
+
+fn main() {
+    let mut vars = HashMap::new();
+    vars.insert("BUILDER_TEST_EXECUTABLE", "cargo");
+    vars.insert("BUILDER_TEST_JOBS", "8");
+
+    let command = CommandBuilder::from_env_with(|var| vars.get(var).map(|v| v.to_string()))
+        .unwrap()
+        .arg("build".to_owned())
+        .build()
+        .unwrap();
+    assert_eq!(command.executable, "cargo");
+    assert_eq!(command.jobs, 8);
+    assert_eq!(command.target_dir, None);
+    assert_eq!(command.args, ["build"]);
+
+    vars.insert("BUILDER_TEST_JOBS", "eight");
+    let err = CommandBuilder::from_env_with(|var| vars.get(var).map(|v| v.to_string()))
+        .err()
+        .unwrap();
+    match &err {
+        CommandBuilderError::InvalidEnv { field, var, .. } => {
+            assert_eq!(*field, "jobs");
+            assert_eq!(*var, "BUILDER_TEST_JOBS");
+        }
+        _ => unreachable!(),
+    }
+    assert_eq!(
+        err.to_string(),
+        "invalid value for `jobs` in environment variable `BUILDER_TEST_JOBS`: \
+         invalid digit found in string",
+    );
+
+    // Fields whose variables are not set still have to be provided.
+    let err = CommandBuilder::from_env().unwrap().build().err().unwrap();
+    assert_eq!(err.to_string(), "missing fields: executable, jobs");
+}
@@ -0,0 +1,60 @@
+// Tuple structs, unit structs and generic structs get builders too.
+//
+// The setters of positional fields are named `_0`, `_1`, ... unless the field
+// has #[builder(name = "...")], which names both the setter and the builder's
+// field. A unit struct's builder has nothing to set and builds right away.
+//
+// The builder carries the struct's lifetimes, type and const parameters and
+// where-clause. In typestate mode the state parameters come after them.
+
+use derive_builder::Builder;
+use std::fmt::Debug;
+
+#[derive(Builder, Debug, PartialEq)]
+pub struct Span<'a, T: Clone>(#[builder(name = "text")] &'a str, T, Option<usize>);
+
+#[derive(Builder, Debug, PartialEq)]
+pub struct Unit;
+
+#[derive(Builder, Debug, PartialEq)]
+pub struct Grid<T, const N: usize>
+where
+    T: Debug + Clone + Default,
+{
+    cells: [T; N],
+    #[builder(default)]
+    label: T,
+}
+
+#[derive(Builder, Debug, PartialEq)]
+#[builder(typestate)]
+pub struct Pair<'a, T>(&'a str, T, #[builder(default)] Vec<T>);
+
+fn main() {
+    let text = String::from("hello");
+
+    let span = Span::builder().text(&text)._1(7u8).build().unwrap();
+    assert_eq!(span, Span("hello", 7, None));
+
+    let span = Span::builder()
+        .text(&text[1..])
+        ._1(String::from("x"))
+        ._2(3)
+        .build()
+        .unwrap();
+    assert_eq!(span, Span("ello", "x".to_owned(), Some(3)));
+
+    assert_eq!(Unit::builder().build().unwrap(), Unit);
+
+    let grid = Grid::<u8, 3>::builder().cells([1, 2, 3]).build().unwrap();
+    assert_eq!(
+        grid,
+        Grid {
+            cells: [1, 2, 3],
+            label: 0,
+        }
+    );
+
+    let pair = Pair::builder()._1(2)._0(&text).build();
+    assert_eq!(pair, Pair("hello", 2, Vec::new()));
+}
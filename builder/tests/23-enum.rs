@@ -0,0 +1,72 @@
+// On an enum, the derive generates a builder for every variant, named after
+// the enum and the variant: `Request::get_builder()` returns a
+// `RequestGetBuilder` whose build() returns a `Request::Get`.
+//
+// Variant fields support the same attributes as struct fields, and
+// #[builder(...)] on a variant applies to that variant's builder in addition
+// to whatever is on the enum. Tuple variants get positional setters like
+// tuple structs do, and unit variants a builder with nothing to set.
+//
+// Every variant's builder has all of a generic enum's parameters, including
+// those its own fields don't use.
+
+use derive_builder::Builder;
+
+#[derive(Builder, Debug, PartialEq)]
+pub enum Request {
+    Get {
+        url: String,
+        #[builder(each = "header")]
+        headers: Vec<(String, String)>,
+        timeout: Option<u64>,
+    },
+    #[builder(typestate)]
+    Post { url: String, body: Vec<u8> },
+    Raw(#[builder(name = "line")] String),
+    Ping,
+}
+
+#[derive(Builder, Debug, PartialEq)]
+pub enum Msg<T: Clone> {
+    Data { value: T },
+    Ping { id: u32 },
+}
+
+fn main() {
+    let get = Request::get_builder()
+        .url("https://example.com".to_owned())
+        .header(("accept".to_owned(), "*/*".to_owned()))
+        .build()
+        .unwrap();
+    assert_eq!(
+        get,
+        Request::Get {
+            url: "https://example.com".to_owned(),
+            headers: vec![("accept".to_owned(), "*/*".to_owned())],
+            timeout: None,
+        }
+    );
+
+    let err = Request::get_builder().timeout(5).build().unwrap_err();
+    assert_eq!(err.to_string(), "`url` must be initialized");
+    let _: RequestGetBuilderError = err;
+
+    let post = Request::post_builder()
+        .body(b"{}".to_vec())
+        .url("https://example.com".to_owned())
+        .build();
+    assert!(matches!(post, Request::Post { .. }));
+
+    let raw = Request::raw_builder()
+        .line("GET / HTTP/1.1".to_owned())
+        .build()
+        .unwrap();
+    assert_eq!(raw, Request::Raw("GET / HTTP/1.1".to_owned()));
+
+    assert_eq!(Request::ping_builder().build().unwrap(), Request::Ping);
+
+    let data = Msg::data_builder().value("payload").build().unwrap();
+    assert_eq!(data, Msg::Data { value: "payload" });
+    let ping: Msg<String> = Msg::ping_builder().id(7).build().unwrap();
+    assert_eq!(ping, Msg::Ping { id: 7 });
+}
@@ -0,0 +1,134 @@
+// #[derive_builder::builder] on a function generates a builder for calling
+// it with named arguments: `connect_builder()` returns a builder with a setter
+// per parameter, and its `call()` calls `connect` with them.
+//
+// Parameters behave like the fields of a #[builder(typestate)] struct, so a
+// required argument that was never given is a compile error. `Option`
+// parameters default to `None`, and #[builder(each = "...")] and
+// #[builder(default)] work on parameters too. Options for the whole builder,
+// like `setter(into)`, go in the attribute's arguments.
+//
+// For methods, the attribute goes on the impl block and each method to build
+// is marked with #[builder]. The builder holds on to the receiver, so
+// `client.send_builder()` borrows `client` until `call()`, and to borrowed
+// arguments, whose elided lifetimes become parameters of the builder.
+
+use std::time::Duration;
+
+#[derive(Debug, PartialEq)]
+pub struct Conn {
+    host: String,
+    port: u16,
+    timeout: Option<Duration>,
+    options: Vec<String>,
+}
+
+#[derive_builder::builder(setter(into))]
+pub fn connect(
+    host: String,
+    #[builder(default = "80")] port: u16,
+    timeout: Option<Duration>,
+    #[builder(each = "option")] options: Vec<String>,
+) -> Conn {
+    Conn {
+        host,
+        port,
+        timeout,
+        options,
+    }
+}
+
+#[derive_builder::builder]
+fn first_word(text: &str, #[builder(default = "' '")] separator: char) -> &str {
+    text.split(separator).next().unwrap_or_default()
+}
+
+#[derive_builder::builder]
+fn largest<T: PartialOrd>(items: Vec<T>) -> Option<T> {
+    items
+        .into_iter()
+        .fold(None, |max, item| match max {
+            Some(max) if max >= item => Some(max),
+            _ => Some(item),
+        })
+}
+
+pub struct Client {
+    base: String,
+    sent: Vec<String>,
+}
+
+#[derive_builder::builder]
+impl Client {
+    #[builder]
+    pub fn new(base: String) -> Self {
+        Client {
+            base,
+            sent: Vec::new(),
+        }
+    }
+
+    #[builder]
+    pub fn url(&self, path: &str, query: Option<&str>) -> String {
+        match query {
+            Some(query) => format!("{}{}?{}", self.base, path, query),
+            None => format!("{}{}", self.base, path),
+        }
+    }
+
+    #[builder(setter(into))]
+    pub fn send(&mut self, path: String, #[builder(default)] retries: u32) -> &str {
+        self.sent.push(format!("{} x{}", path, retries + 1));
+        self.sent.last().unwrap()
+    }
+
+    #[builder]
+    pub fn into_sent(self, #[builder(default)] skip: usize) -> Vec<String> {
+        self.sent.into_iter().skip(skip).collect()
+    }
+}
+
+fn main() {
+    let conn = connect_builder()
+        .host("localhost")
+        .option("nodelay")
+        .option("keepalive")
+        .call();
+    assert_eq!(
+        conn,
+        Conn {
+            host: "localhost".to_owned(),
+            port: 80,
+            timeout: None,
+            options: vec!["nodelay".to_owned(), "keepalive".to_owned()],
+        }
+    );
+
+    let conn = connect_builder()
+        .timeout(Duration::from_secs(5))
+        .port(8080u16)
+        .host("example.com")
+        .call();
+    assert_eq!(conn.port, 8080);
+    assert_eq!(conn.timeout, Some(Duration::from_secs(5)));
+
+    let text = String::from("hello world");
+    assert_eq!(first_word_builder().text(&text).call(), "hello");
+    let word = first_word_builder().separator('o').text(&text).call();
+    assert_eq!(word, "hell");
+
+    assert_eq!(largest_builder().items(vec![3, 9, 4]).call(), Some(9));
+
+    let mut client = Client::new_builder()
+        .base("https://example.com".to_owned())
+        .call();
+
+    let url = client.url_builder().path("/search").query("q=rust").call();
+    assert_eq!(url, "https://example.com/search?q=rust");
+
+    assert_eq!(client.send_builder().path("/a").call(), "/a x1");
+    assert_eq!(client.send_builder().retries(2u32).path("/b").call(), "/b x3");
+
+    let sent = client.into_sent_builder().skip(1).call();
+    assert_eq!(sent, vec!["/b x3".to_owned()]);
+}
@@ -0,0 +1,52 @@
+// build() checks every required field before failing, so that a config with
+// several mistakes can be fixed in one go. A single missing field is still
+// reported as UninitializedField; when more than one is missing, the error is
+// MissingFields with all of their names in declaration order.
+//
+// The same check is available without building through missing_fields(),
+// for example to show which fields a form still needs.
+
+use derive_builder::Builder;
+
+#[derive(Builder, Debug)]
+pub struct Command {
+    executable: String,
+    #[builder(each = "arg")]
+    args: Vec<String>,
+    env: Vec<String>,
+    #[builder(default)]
+    jobs: u32,
+    current_dir: Option<String>,
+    timeout: u64,
+}
+
+#[derive(Builder, Debug)]
+pub struct Optional {
+    name: Option<String>,
+}
+
+fn main() {
+    let mut builder = Command::builder();
+    assert_eq!(builder.missing_fields(), ["executable", "env", "timeout"]);
+
+    let err = builder.arg("build".to_owned()).build().unwrap_err();
+    assert_eq!(err.to_string(), "missing fields: executable, env, timeout");
+    match err {
+        CommandBuilderError::MissingFields(fields) => {
+            assert_eq!(fields, ["executable", "env", "timeout"]);
+        }
+        err => panic!("unexpected error: {}", err),
+    }
+
+    builder.executable("cargo".to_owned()).env(vec![]);
+    assert_eq!(builder.missing_fields(), ["timeout"]);
+    let err = builder.build().unwrap_err();
+    assert!(matches!(err, CommandBuilderError::UninitializedField("timeout")));
+    assert_eq!(err.to_string(), "`timeout` must be initialized");
+
+    builder.timeout(30);
+    assert!(builder.missing_fields().is_empty());
+    assert_eq!(builder.build().unwrap().args, ["build"]);
+
+    assert!(Optional::builder().missing_fields().is_empty());
+}
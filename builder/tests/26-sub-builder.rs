@@ -0,0 +1,121 @@
+// A field whose type derives Builder can be given #[builder(sub_builder)].
+// Its setter then takes a closure that configures the nested builder in
+// place, and the outer builder keeps that nested builder until build(), which
+// builds it along with everything else. A sub-builder field that is never set
+// is built from an empty nested builder, unless it has a default.
+//
+// When the nested build fails, the error is the SubBuilder variant, whose
+// message names the nested fields by their path from the outer struct, however
+// deeply they are nested. missing_fields() names them the same way.
+
+use derive_builder::Builder;
+
+#[derive(Builder, Debug, PartialEq)]
+pub struct Retry {
+    attempts: u32,
+    backoff_ms: u64,
+}
+
+#[derive(Builder, Debug, PartialEq)]
+pub struct Config {
+    timeout: u64,
+    #[builder(default)]
+    verbose: bool,
+    #[builder(sub_builder)]
+    retry: Retry,
+}
+
+#[derive(Builder, Debug, PartialEq)]
+pub struct Server {
+    host: String,
+    #[builder(sub_builder)]
+    config: Config,
+    #[builder(sub_builder, default = "Retry { attempts: 1, backoff_ms: 0 }")]
+    reconnect: Retry,
+}
+
+fn main() {
+    let mut builder = Server::builder();
+    builder
+        .host("localhost".to_owned())
+        .config(|config: &mut ConfigBuilder| {
+            config.timeout(30).retry(|retry| {
+                retry.attempts(3).backoff_ms(100);
+            });
+        });
+    builder.config(|config| {
+        config.verbose(true);
+    });
+
+    let server = builder.build().unwrap();
+    assert_eq!(
+        server,
+        Server {
+            host: "localhost".to_owned(),
+            config: Config {
+                timeout: 30,
+                verbose: true,
+                retry: Retry {
+                    attempts: 3,
+                    backoff_ms: 100,
+                },
+            },
+            reconnect: Retry {
+                attempts: 1,
+                backoff_ms: 0,
+            },
+        }
+    );
+
+    let err = Server::builder()
+        .host("localhost".to_owned())
+        .config(|config| {
+            config.verbose(true);
+        })
+        .build()
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "missing fields: config.timeout, config.retry.attempts, config.retry.backoff_ms",
+    );
+
+    let err = Config::builder().timeout(5).build().unwrap_err();
+    assert_eq!(err.to_string(), "missing fields: retry.attempts, retry.backoff_ms");
+    match err {
+        ConfigBuilderError::SubBuilder { field, missing, .. } => {
+            assert_eq!(field, "retry");
+            assert_eq!(missing, ["attempts", "backoff_ms"]);
+        }
+        err => panic!("unexpected error: {}", err),
+    }
+
+    let mut builder = Server::builder();
+    assert_eq!(
+        builder.missing_fields(),
+        ["host", "config.timeout", "config.retry.attempts", "config.retry.backoff_ms"],
+    );
+    builder.host("localhost".to_owned()).config(|config| {
+        config.timeout(5);
+    });
+    assert_eq!(
+        builder.missing_fields(),
+        ["config.retry.attempts", "config.retry.backoff_ms"],
+    );
+    let err = builder.build().unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "missing fields: config.retry.attempts, config.retry.backoff_ms",
+    );
+    match err {
+        ServerBuilderError::SubBuilder { field, missing, .. } => {
+            assert_eq!(field, "config");
+            assert_eq!(missing, ["retry.attempts", "retry.backoff_ms"]);
+        }
+        err => panic!("unexpected error: {}", err),
+    }
+
+    let server = server.to_builder().reconnect(|r| {
+        r.attempts(5);
+    }).build().unwrap();
+    assert_eq!(server.reconnect.attempts, 5);
+}
@@ -0,0 +1,98 @@
+// Struct-level groups constrain which fields may be set together:
+//
+//   #[builder(group(name = "source", exactly_one(source_path, source_bytes)))]
+//   #[builder(group(name = "tls", requires_all(tls_cert, tls_key)))]
+//
+// build() fails with GroupNotSet or GroupConflict when an `exactly_one` group
+// does not have exactly one of its fields set, and with GroupIncomplete when
+// only some of a `requires_all` group's fields are set.
+//
+// Typestate builders check groups at compile time instead: build() only
+// exists once the group's fields are in an allowed combination.
+
+use derive_builder::Builder;
+
+#[derive(Builder, Debug)]
+#[builder(group(name = "source", exactly_one(source_path, source_bytes)))]
+#[builder(group(name = "tls", requires_all(tls_cert, tls_key)))]
+pub struct Input {
+    source_path: Option<String>,
+    source_bytes: Option<Vec<u8>>,
+    tls_cert: Option<String>,
+    tls_key: Option<String>,
+}
+
+#[derive(Builder, Debug)]
+#[builder(typestate)]
+#[builder(group(name = "source", exactly_one(source_path, source_bytes)))]
+#[builder(group(name = "tls", requires_all(tls_cert, tls_key)))]
+pub struct Checked {
+    name: String,
+    source_path: Option<String>,
+    source_bytes: Option<Vec<u8>>,
+    tls_cert: Option<String>,
+    tls_key: Option<String>,
+}
+
+fn main() {
+    let input = Input::builder()
+        .source_path("in.txt".to_owned())
+        .build()
+        .unwrap();
+    assert_eq!(input.source_path.as_deref(), Some("in.txt"));
+
+    let err = Input::builder().build().unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "exactly one of source_path, source_bytes must be set (group `source`)",
+    );
+    assert!(matches!(err, InputBuilderError::GroupNotSet { group: "source", .. }));
+
+    let err = Input::builder()
+        .source_path("in.txt".to_owned())
+        .source_bytes(vec![1])
+        .build()
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "only one of source_path, source_bytes may be set (group `source`)",
+    );
+
+    let err = Input::builder()
+        .source_bytes(vec![1])
+        .tls_cert("cert.pem".to_owned())
+        .build()
+        .unwrap_err();
+    match err {
+        InputBuilderError::GroupIncomplete { group, missing } => {
+            assert_eq!(group, "tls");
+            assert_eq!(missing, ["tls_key"]);
+        }
+        err => panic!("unexpected error: {}", err),
+    }
+
+    let input = Input::builder()
+        .source_bytes(vec![1])
+        .tls_cert("cert.pem".to_owned())
+        .tls_key("key.pem".to_owned())
+        .build()
+        .unwrap();
+    assert_eq!(input.tls_key.as_deref(), Some("key.pem"));
+
+    let checked = Checked::builder()
+        .source_bytes(vec![1, 2])
+        .name("a".to_owned())
+        .build();
+    assert_eq!(checked.source_bytes, Some(vec![1, 2]));
+    assert_eq!(checked.source_path, None);
+    assert_eq!(checked.tls_cert, None);
+
+    let checked = Checked::builder()
+        .tls_key("key.pem".to_owned())
+        .name("b".to_owned())
+        .source_path("in.txt".to_owned())
+        .tls_cert("cert.pem".to_owned())
+        .build();
+    assert_eq!(checked.tls_cert.as_deref(), Some("cert.pem"));
+    assert_eq!(checked.source_path.as_deref(), Some("in.txt"));
+}
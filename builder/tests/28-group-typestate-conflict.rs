@@ -0,0 +1,19 @@
+// In typestate mode, setting two fields of an `exactly_one` group leaves the
+// builder in a state for which build() does not exist.
+
+use derive_builder::Builder;
+
+#[derive(Builder)]
+#[builder(typestate)]
+#[builder(group(name = "source", exactly_one(source_path, source_bytes)))]
+pub struct Input {
+    source_path: Option<String>,
+    source_bytes: Option<Vec<u8>>,
+}
+
+fn main() {
+    let _input = Input::builder()
+        .source_path("in.txt".to_owned())
+        .source_bytes(vec![1])
+        .build();
+}
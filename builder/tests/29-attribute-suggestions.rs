@@ -0,0 +1,24 @@
+// Every mistake in the builder attributes is reported in one go, with a
+// suggestion when a key looks like a misspelling of a real one. The builder
+// is still generated without the offending attributes, so code using it
+// does not add errors of its own.
+
+use derive_builder::Builder;
+
+#[derive(Builder)]
+#[builder(patern = "owned", setter(int))]
+pub struct Command {
+    executable: String,
+    #[builder(eac = "arg")]
+    args: Vec<String>,
+    #[builder(default, setter(strip_optoin = false))]
+    env: Vec<String>,
+    #[builder(each)]
+    current_dir: Option<String>,
+    #[builder(frobnicate)]
+    jobs: u32,
+}
+
+fn main() {
+    let _ = Command::builder().executable("cargo".to_owned()).build();
+}
@@ -0,0 +1,59 @@
+// The builder can be inspected and partly cleared. Every field has a
+// `get_<field>()` getter returning what has been set, if anything, and an
+// `unset_<field>()` method, and `reset()` unsets them all.
+//
+// #[builder(derive(...))] derives traits for the builder. Its Debug shows the
+// fields that have not been set as `<unset>`.
+
+use derive_builder::Builder;
+
+#[derive(Builder, Debug)]
+#[builder(derive(Debug, Clone, PartialEq))]
+pub struct Command {
+    executable: String,
+    #[builder(each = "arg")]
+    args: Vec<String>,
+    current_dir: Option<String>,
+    #[builder(default = "1")]
+    jobs: u32,
+}
+
+#[derive(Builder)]
+#[builder(pattern = "owned", derive(Clone, Debug))]
+pub struct Pair<T> {
+    left: T,
+    right: T,
+}
+
+fn main() {
+    let mut builder = Command::builder();
+    builder.executable("cargo".to_owned()).arg("build".to_owned());
+
+    assert_eq!(builder.get_executable().map(String::as_str), Some("cargo"));
+    assert_eq!(builder.get_args().map(Vec::len), Some(1));
+    assert_eq!(builder.get_current_dir(), None);
+    // Defaults are only applied by build().
+    assert_eq!(builder.get_jobs(), None);
+
+    assert_eq!(
+        format!("{:?}", builder),
+        r#"CommandBuilder { executable: "cargo", args: ["build"], current_dir: <unset>, jobs: <unset> }"#,
+    );
+
+    let snapshot = builder.clone();
+    assert_eq!(snapshot, builder);
+
+    builder.unset_executable();
+    assert_eq!(builder.get_executable(), None);
+    assert_ne!(snapshot, builder);
+    assert!(builder.build().is_err());
+
+    builder.current_dir("..".to_owned()).reset();
+    assert_eq!(builder, Command::builder());
+
+    let pair = Pair::builder().left(1).right(2);
+    let left = pair.clone().unset_right();
+    assert_eq!(left.get_left(), Some(&1));
+    assert_eq!(format!("{:?}", left), "PairBuilder { left: 1, right: <unset> }");
+    assert_eq!(pair.build().unwrap().right, 2);
+}
@@ -0,0 +1,65 @@
+// #[builder(no_std)] generates a builder that only refers to `core` and
+// `alloc`, for crates without the standard library. Those need
+// `extern crate alloc;` for the builder's use of `String` and `Vec`.
+//
+// As in 09-redefined-prelude-types.rs, every path in the expansion has to be
+// absolute, and here none of them may go through `std`. The test links `std`
+// only so that it can run as a binary, under another name, so that a path to
+// `::std` in the expansion does not resolve. Local modules named `core` and
+// `std` must not be mistaken for the crates either.
+
+#![no_std]
+
+extern crate alloc;
+extern crate std as host;
+
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use derive_builder::Builder;
+
+type Option = ();
+type Some = ();
+type None = ();
+type Result = ();
+type Box = ();
+
+mod core {}
+mod std {}
+
+#[derive(Builder)]
+#[builder(no_std, derive(Debug, PartialEq))]
+pub struct Command {
+    executable: String,
+    #[builder(each = "arg")]
+    args: Vec<String>,
+    current_dir: ::core::option::Option<String>,
+    #[builder(default = "1")]
+    jobs: u32,
+    #[builder(default)]
+    cache: BTreeMap<String, u32>,
+}
+
+#[derive(Builder)]
+#[builder(no_std, typestate)]
+pub struct Pair {
+    left: u8,
+    right: u8,
+}
+
+fn main() {
+    let mut builder = Command::builder();
+    builder.executable("cargo".to_string()).arg("build".to_string());
+    let command = builder.build().unwrap();
+    assert_eq!(command.executable, "cargo");
+    assert_eq!(command.args, ["build"]);
+    assert!(command.current_dir.is_none());
+    assert_eq!(command.jobs, 1);
+    assert!(command.cache.is_empty());
+
+    let err = Command::builder().build().err().unwrap();
+    assert_eq!(err.to_string(), "`executable` must be initialized");
+
+    let pair = Pair::builder().left(1).right(2).build();
+    assert_eq!(pair.left + pair.right, 3);
+}
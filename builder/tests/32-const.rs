@@ -0,0 +1,53 @@
+// #[builder(const)] makes `builder()` and the setters `const fn` and adds
+// `build_const()`, so that values can be built in constants and statics.
+// Fields must have types that are usable in const fn.
+
+use derive_builder::Builder;
+
+#[derive(Builder, Debug, PartialEq)]
+#[builder(const, group(name = "source", exactly_one(path, url)))]
+pub struct Command {
+    executable: &'static str,
+    #[builder(default = "1")]
+    jobs: u32,
+    current_dir: Option<&'static str>,
+    #[builder(setter(strip_option = false))]
+    target: Option<&'static str>,
+    path: Option<&'static str>,
+    url: Option<&'static str>,
+}
+
+const BUILD: Command = Command::builder()
+    .executable("cargo")
+    .current_dir("..")
+    .path("Cargo.toml")
+    .build_const();
+
+static COMMANDS: [Command; 2] = [
+    BUILD,
+    Command::builder()
+        .executable("rustc")
+        .jobs(4)
+        .target(Some("wasm32-unknown-unknown"))
+        .url("https://example.com")
+        .build_const(),
+];
+
+fn main() {
+    assert_eq!(BUILD.executable, "cargo");
+    assert_eq!(BUILD.jobs, 1);
+    assert_eq!(BUILD.current_dir, Some(".."));
+    assert_eq!(BUILD.target, None);
+
+    assert_eq!(COMMANDS[1].jobs, 4);
+    assert_eq!(COMMANDS[1].target, Some("wasm32-unknown-unknown"));
+
+    // The usual methods are still there at runtime.
+    let command = Command::builder()
+        .executable("cargo")
+        .current_dir("..")
+        .path("Cargo.toml")
+        .build()
+        .unwrap();
+    assert_eq!(command, BUILD);
+}
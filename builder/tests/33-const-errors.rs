@@ -0,0 +1,33 @@
+// A required field missing from a const builder is a compile-time error, and
+// so are `each` fields, which need to allocate. A field type with a
+// destructor, which const fn cannot run, is reported at the field, and at the
+// struct's name for build_const(), which consumes the builder.
+
+use derive_builder::Builder;
+
+#[derive(Builder)]
+#[builder(const)]
+pub struct Command {
+    executable: &'static str,
+    jobs: u32,
+}
+
+#[derive(Builder)]
+#[builder(const)]
+pub struct Args {
+    #[builder(each = "arg")]
+    args: Vec<&'static str>,
+}
+
+#[derive(Builder)]
+#[builder(const)]
+pub struct Job {
+    name: String,
+    retries: u32,
+}
+
+const COMMAND: Command = Command::builder().executable("cargo").build_const();
+
+fn main() {
+    let _ = COMMAND.jobs;
+}
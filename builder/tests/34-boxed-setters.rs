@@ -0,0 +1,75 @@
+// With #[builder(setter(boxed))], setters of fields written as
+// `Box<dyn Trait>`, `Arc<dyn Trait>` or `Rc<dyn Trait>` take any
+// implementation of the trait and wrap it, so that callers need not write
+// `Box::new` themselves. This applies to optional fields, to the elements
+// added by `each` setters and to typestate setters.
+//
+// Without it the setter takes the wrapper itself, as for any other type. A
+// value that is already boxed is only accepted by a boxed setter where the
+// trait is implemented for the box, as for closures, and then gets wrapped a
+// second time.
+//
+// Boxed trait objects are rarely Clone, so these builders use the owned
+// pattern, whose build() does not clone.
+
+use derive_builder::Builder;
+use std::fmt::Display;
+use std::rc::Rc;
+use std::sync::Arc;
+
+pub trait Log {
+    fn log(&self, message: &str) -> String;
+}
+
+struct Prefix(&'static str);
+
+impl Log for Prefix {
+    fn log(&self, message: &str) -> String {
+        format!("{}{}", self.0, message)
+    }
+}
+
+#[derive(Builder)]
+#[builder(pattern = "owned")]
+pub struct Server {
+    #[builder(setter(boxed))]
+    logger: Box<dyn Log>,
+    #[builder(setter(boxed))]
+    handler: Arc<dyn Fn(&str) -> usize + Send + Sync>,
+    #[builder(setter(boxed))]
+    fallback: Option<Rc<dyn Log>>,
+    #[builder(each = "hook", setter(boxed))]
+    hooks: Vec<Box<dyn Fn(u32) -> u32>>,
+    name: Box<dyn Display>,
+}
+
+#[derive(Builder)]
+#[builder(typestate)]
+pub struct Job<'a> {
+    #[builder(setter(boxed))]
+    task: Box<dyn FnOnce() -> &'a str + 'a>,
+}
+
+fn main() {
+    let decrement: Box<dyn Fn(u32) -> u32> = Box::new(|n| n - 1);
+    let server = Server::builder()
+        .logger(Prefix("> "))
+        .handler(|request: &str| request.len())
+        .fallback(Prefix("! "))
+        .hook(|n| n + 1)
+        .hook(|n| n * 2)
+        .hook(decrement)
+        .name(Box::new("api"))
+        .build()
+        .unwrap();
+
+    assert_eq!(server.logger.log("started"), "> started");
+    assert_eq!((server.handler)("GET /"), 5);
+    assert_eq!(server.fallback.unwrap().log("failed"), "! failed");
+    assert_eq!(server.hooks.iter().fold(1, |n, hook| hook(n)), 3);
+    assert_eq!(server.name.to_string(), "api");
+
+    let name = String::from("build");
+    let job = Job::builder().task(|| name.as_str()).build();
+    assert_eq!((job.task)(), "build");
+}
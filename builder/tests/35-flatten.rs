@@ -0,0 +1,120 @@
+// A field with #[builder(flatten)] is set through the setters of its own
+// builder, which appear directly on the outer builder: `.port(..)` rather
+// than `.net(|net| net.port(..))`. Its required fields are reported by the
+// outer builder, named by their path as in `net.host`. Merging two outer
+// builders merges the flattened ones too, as their setters are the outer
+// builder's.
+//
+// The flattened struct opts in with #[builder(flattenable)], and has to be in
+// the same crate. As with `sub_builder`, its builder has to be nameable by the
+// path the field's type is written with. The types of its fields need not
+// be: the forwarded setters only name them through that builder.
+
+use derive_builder::Builder;
+
+mod net {
+    use derive_builder::Builder;
+    use std::net::Ipv4Addr;
+
+    #[derive(Builder, Debug, PartialEq)]
+    #[builder(flattenable)]
+    pub struct NetConfig {
+        pub host: String,
+        #[builder(default = "80")]
+        pub port: u16,
+        #[builder(each = "alias")]
+        pub aliases: Vec<String>,
+        #[builder(default = "Ipv4Addr::UNSPECIFIED", setter(into))]
+        pub addr: Ipv4Addr,
+    }
+
+    #[derive(Builder, Debug, PartialEq)]
+    #[builder(pattern = "owned", flattenable)]
+    pub struct Limits {
+        #[builder(setter(try_into))]
+        pub connections: u32,
+    }
+}
+
+#[derive(Builder, Debug)]
+pub struct Server {
+    name: String,
+    #[builder(flatten)]
+    net: net::NetConfig,
+    retries: Option<u32>,
+}
+
+// Builders in the owned pattern can flatten those in any pattern.
+#[derive(Builder, Debug)]
+#[builder(pattern = "owned")]
+pub struct Client {
+    #[builder(flatten)]
+    net: net::NetConfig,
+    #[builder(flatten)]
+    limits: net::Limits,
+}
+
+fn main() {
+    let server = Server::builder()
+        .name("api".to_owned())
+        .host("localhost".to_owned())
+        .alias("api.local".to_owned())
+        .retries(3)
+        .build()
+        .unwrap();
+    assert_eq!(server.name, "api");
+    assert_eq!(server.net.host, "localhost");
+    assert_eq!(server.net.port, 80);
+    assert_eq!(server.net.aliases, ["api.local"]);
+    assert!(server.net.addr.is_unspecified());
+    assert_eq!(server.retries, Some(3));
+
+    let err = Server::builder().port(8080).build().unwrap_err();
+    assert_eq!(err.to_string(), "missing fields: name, net.host");
+    let mut builder = Server::builder();
+    builder.name("api".to_owned());
+    assert_eq!(builder.missing_fields(), ["net.host"]);
+    assert_eq!(
+        builder.build().unwrap_err().to_string(),
+        "`net.host` must be initialized",
+    );
+
+    let mut defaults = Server::builder();
+    defaults.host("localhost".to_owned()).name("n".to_owned());
+    let mut cli = Server::builder();
+    cli.port(8080);
+    let server = defaults.merge(cli).build().unwrap();
+    assert_eq!(server.net.host, "localhost");
+    assert_eq!(server.net.port, 8080);
+
+    let client = Client::builder()
+        .host("example.com".to_owned())
+        .port(443)
+        .addr([127, 0, 0, 1])
+        .try_connections(64u64)
+        .unwrap()
+        .build()
+        .unwrap();
+    assert_eq!(client.net.port, 443);
+    assert!(client.net.addr.is_loopback());
+    assert_eq!(client.limits.connections, 64);
+
+    assert!(Client::builder().try_connections(-1).is_err());
+
+    let client = Client::builder()
+        .host("example.com".to_owned())
+        .try_connections(8)
+        .unwrap()
+        .merge(Client::builder().port(443))
+        .build()
+        .unwrap();
+    assert_eq!(client.net.host, "example.com");
+    assert_eq!(client.net.port, 443);
+    assert_eq!(client.limits.connections, 8);
+
+    let err = Client::builder().build().unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "missing fields: net.host, limits.connections",
+    );
+}
@@ -0,0 +1,20 @@
+// A flattened field whose setters clash with those of the outer builder's own
+// fields is an error, reported at both fields.
+
+use derive_builder::Builder;
+
+#[derive(Builder)]
+#[builder(flattenable)]
+pub struct NetConfig {
+    host: String,
+    port: u16,
+}
+
+#[derive(Builder)]
+pub struct Server {
+    #[builder(flatten)]
+    net: NetConfig,
+    port: u16,
+}
+
+fn main() {}
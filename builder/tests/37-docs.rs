@@ -0,0 +1,72 @@
+// The builder, its constructor, `build()` and the setters are documented, so
+// that a crate with #![deny(missing_docs)] can derive builders for its public
+// types. Setters repeat their field's docs along with whether the field is
+// required, and carry the field's #[deprecated].
+//
+// A setter that was renamed can keep its former name with
+// #[builder(alias = "...")], which generates a deprecated setter doing the
+// same thing. Typestate setters get aliases as well.
+
+#![deny(missing_docs, deprecated)]
+
+//! Public types with builders.
+
+use derive_builder::Builder;
+
+/// A request to an HTTP server.
+#[derive(Builder, Debug)]
+pub struct Request {
+    /// The path requested, starting with `/`.
+    #[builder(setter(into), alias = "url", alias = "uri")]
+    pub path: String,
+    /// Seconds to wait for a response.
+    #[builder(default = "30", alias = "timeout_secs")]
+    pub timeout: u64,
+    /// Headers sent along, as name and value.
+    #[builder(each = "header")]
+    pub headers: Vec<(String, String)>,
+    /// Whether to follow redirects.
+    #[deprecated(note = "redirects are always followed")]
+    pub follow: Option<bool>,
+}
+
+/// A job run in the background.
+#[derive(Builder)]
+#[builder(typestate)]
+pub struct Job {
+    /// What to run.
+    #[builder(alias = "cmd")]
+    pub command: &'static str,
+}
+
+fn main() {
+    let request = Request::builder()
+        .path("/")
+        .header(("accept".to_owned(), "*/*".to_owned()))
+        .build()
+        .unwrap();
+    assert_eq!(request.path, "/");
+    assert_eq!(request.timeout, 30);
+    assert_eq!(request.headers.len(), 1);
+
+    #[allow(deprecated)]
+    let request = Request::builder()
+        .url("/index.html")
+        .timeout_secs(5)
+        .follow(false)
+        .build()
+        .unwrap();
+    assert_eq!(request.path, "/index.html");
+    assert_eq!(request.timeout, 5);
+    #[allow(deprecated)]
+    let follow = request.follow;
+    assert_eq!(follow, Some(false));
+
+    #[allow(deprecated)]
+    let request = Request::builder().uri("/about").build().unwrap();
+    assert_eq!(request.path, "/about");
+
+    #[allow(deprecated)]
+    let job = Job::builder().cmd("true").build();
+    assert_eq!(job.command, "true");
+}
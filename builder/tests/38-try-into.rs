@@ -0,0 +1,99 @@
+// #[builder(setter(try_into))] adds a `try_<field>` setter taking anything
+// that converts into the field's type with TryInto. It returns the
+// conversion's error, wrapping the builder in a Result, so it chains with `?`.
+//
+// With #[builder(setter(try_into = "deferred"))] the setter returns the
+// builder like the others and build() reports the failure instead, as
+// `InvalidValue` naming the field, unless the field is set or unset again.
+//
+// Typestate builders get the immediate kind, which also moves a required
+// field to its set state.
+
+use derive_builder::Builder;
+use std::num::{NonZeroU16, TryFromIntError};
+
+#[derive(Builder, Debug)]
+#[builder(derive(Debug))]
+pub struct Listener {
+    #[builder(setter(try_into))]
+    port: NonZeroU16,
+    #[builder(setter(try_into))]
+    backlog: Option<u16>,
+    #[builder(default = "3", setter(try_into = "deferred"))]
+    retries: u8,
+}
+
+#[derive(Builder)]
+#[builder(typestate)]
+pub struct Socket {
+    #[builder(setter(try_into))]
+    port: u16,
+    #[builder(default = "64", setter(try_into))]
+    buffer: u16,
+}
+
+fn listener() -> Result<Listener, Box<dyn std::error::Error>> {
+    let listener = Listener::builder()
+        .try_port(8080u16)?
+        .try_backlog(128u64)?
+        .try_retries(5i64)
+        .build()?;
+    Ok(listener)
+}
+
+fn main() {
+    let listener = listener().unwrap();
+    assert_eq!(listener.port.get(), 8080);
+    assert_eq!(listener.backlog, Some(128));
+    assert_eq!(listener.retries, 5);
+
+    let mut builder = Listener::builder();
+    let error: TryFromIntError = builder.try_port(0u16).unwrap_err();
+    assert_eq!(error.to_string(), "out of range integral type conversion attempted");
+    assert!(builder.get_port().is_none());
+    assert!(builder.try_backlog(70000u64).is_err());
+    assert!(builder.get_backlog().is_none());
+
+    let error = Listener::builder()
+        .port(NonZeroU16::new(80).unwrap())
+        .try_retries(-1)
+        .try_retries(1000)
+        .build()
+        .unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "invalid value for `retries`: out of range integral type conversion attempted",
+    );
+    match error {
+        ListenerBuilderError::InvalidValue { field, .. } => assert_eq!(field, "retries"),
+        error => panic!("unexpected error: {:?}", error),
+    }
+
+    let mut builder = Listener::builder();
+    builder.port(NonZeroU16::new(80).unwrap()).try_retries(1000).try_retries(3);
+    assert_eq!(builder.build().unwrap().retries, 3);
+    builder.try_retries(1000).retries(3);
+    assert_eq!(builder.build().unwrap().retries, 3);
+    builder.try_retries(1000).unset_retries().retries(4);
+    assert_eq!(builder.build().unwrap().retries, 4);
+    builder.retries(5).try_retries(1000);
+    assert!(builder.build().is_err());
+    assert_eq!(builder.get_retries(), Some(&5));
+
+    let mut builder = Listener::builder();
+    builder.port(NonZeroU16::new(80).unwrap()).try_retries(256);
+    builder.reset().port(NonZeroU16::new(80).unwrap());
+    assert_eq!(builder.build().unwrap().retries, 3);
+
+    let socket = Socket::builder()
+        .try_port(443u64)
+        .unwrap()
+        .try_buffer(4096u32)
+        .unwrap()
+        .build();
+    assert_eq!(socket.port, 443);
+    assert_eq!(socket.buffer, 4096);
+
+    assert!(Socket::builder().try_port(-1i32).is_err());
+    assert!(Socket::builder().try_buffer(1u64 << 20).is_err());
+}
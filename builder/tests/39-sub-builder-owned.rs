@@ -0,0 +1,26 @@
+// A builder with the owned pattern takes itself by value in its setters and
+// is not Clone, so it cannot be a sub_builder, nor be flattened into a builder
+// that does not use the owned pattern either. Each is a single error, at the
+// field's type.
+
+use derive_builder::Builder;
+
+#[derive(Builder)]
+#[builder(pattern = "owned", flattenable)]
+pub struct Retry {
+    attempts: u32,
+}
+
+#[derive(Builder)]
+pub struct Config {
+    #[builder(sub_builder)]
+    retry: Retry,
+}
+
+#[derive(Builder)]
+pub struct Server {
+    #[builder(flatten)]
+    retry: Retry,
+}
+
+fn main() {}
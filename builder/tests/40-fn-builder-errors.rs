@@ -0,0 +1,29 @@
+// A parameter that cannot become a setter is reported at the parameter, and
+// the impl block is still emitted with the builders of its other methods,
+// so the code using them doesn't fail too.
+
+pub struct Client {
+    base: String,
+}
+
+#[derive_builder::builder]
+impl Client {
+    #[builder]
+    pub fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base, path)
+    }
+
+    #[builder]
+    pub fn send(&self, #[builder(default)] (retries, delay): (u32, u32)) -> u32 {
+        retries * delay
+    }
+}
+
+fn main() {
+    let client = Client {
+        base: "https://example.com".to_owned(),
+    };
+    let url = client.url_builder().path("/a").call();
+    assert_eq!(url, client.url("/a"));
+    assert_eq!(client.send((3, 2)), 6);
+}
@@ -0,0 +1,20 @@
+// A setter cannot have the name of one of the builder's own methods. The clash
+// is reported at the field, or at the `each` or alias naming the setter.
+
+use derive_builder::Builder;
+
+#[derive(Builder)]
+pub struct Timer {
+    reset: bool,
+    #[builder(alias = "merge")]
+    combine: bool,
+}
+
+#[derive(Builder)]
+pub struct Counter {
+    #[builder(each = "reset")]
+    resets: Vec<u64>,
+    missing_fields: Vec<String>,
+}
+
+fn main() {}
@@ -0,0 +1,20 @@
+// The methods of a #[deprecated] field's builder are deprecated along with it:
+// its setters, and its getter and `unset_<field>()`.
+
+#![deny(deprecated)]
+
+use derive_builder::Builder;
+
+#[derive(Builder)]
+pub struct Request {
+    pub path: String,
+    #[deprecated(note = "redirects are always followed")]
+    pub follow: Option<bool>,
+}
+
+fn main() {
+    let mut builder = Request::builder();
+    builder.path("/".to_owned()).follow(false);
+    let _ = builder.get_follow();
+    builder.unset_follow();
+}
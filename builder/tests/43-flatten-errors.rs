@@ -0,0 +1,41 @@
+// Only a struct with #[builder(flattenable)] comes with the macro that
+// `flatten` needs, and that macro is private to its crate. A struct from
+// another crate, which a path starting with `::` always names, can be nested
+// with `sub_builder` instead; `extern crate self` stands in for one here. A
+// generic or typestate builder cannot be flattened.
+
+extern crate self as other;
+
+use derive_builder::Builder;
+
+#[derive(Builder)]
+#[builder(flattenable)]
+pub struct Pair<T> {
+    first: T,
+    second: T,
+}
+
+#[derive(Builder)]
+#[builder(typestate, flattenable)]
+pub struct Token {
+    value: String,
+}
+
+#[derive(Builder)]
+pub struct Retry {
+    attempts: u32,
+}
+
+#[derive(Builder)]
+pub struct Client {
+    #[builder(flatten)]
+    retry: Retry,
+}
+
+#[derive(Builder)]
+pub struct Server {
+    #[builder(flatten)]
+    pair: ::other::Pair<u32>,
+}
+
+fn main() {}
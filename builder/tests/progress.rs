@@ -1,13 +1,49 @@
 #[test]
 fn tests() {
     let t = trybuild::TestCases::new();
-    //t.pass("tests/01-parse.rs");
-    //t.pass("tests/02-create-builder.rs");
-    //t.pass("tests/03-call-setters.rs");
-    //t.pass("tests/04-call-build.rs");
-    //t.pass("tests/05-method-chaining.rs");
-    //t.pass("tests/06-optional-field.rs");
-    //t.pass("tests/07-repeated-field.rs");
-    //t.compile_fail("tests/08-unrecognized-attribute.rs");
-    //t.pass("tests/09-redefined-prelude-types.rs");
+    t.pass("tests/01-parse.rs");
+    t.pass("tests/02-create-builder.rs");
+    t.pass("tests/03-call-setters.rs");
+    t.pass("tests/04-call-build.rs");
+    t.pass("tests/05-method-chaining.rs");
+    t.pass("tests/06-optional-field.rs");
+    t.pass("tests/07-repeated-field.rs");
+    t.compile_fail("tests/08-unrecognized-attribute.rs");
+    t.pass("tests/09-redefined-prelude-types.rs");
+    t.pass("tests/10-typestate.rs");
+    t.compile_fail("tests/11-typestate-missing-field.rs");
+    t.pass("tests/12-default.rs");
+    t.pass("tests/13-error-type.rs");
+    t.pass("tests/14-validate.rs");
+    t.pass("tests/15-setter-into.rs");
+    t.pass("tests/16-builder-pattern.rs");
+    t.pass("tests/17-each-collections.rs");
+    t.pass("tests/18-to-builder.rs");
+    t.pass("tests/19-merge.rs");
+    if cfg!(feature = "serde") {
+        t.pass("tests/20-serde.rs");
+    }
+    t.pass("tests/21-env.rs");
+    t.pass("tests/22-tuple-generic.rs");
+    t.pass("tests/23-enum.rs");
+    t.pass("tests/24-fn-builder.rs");
+    t.pass("tests/25-missing-fields.rs");
+    t.pass("tests/26-sub-builder.rs");
+    t.pass("tests/27-groups.rs");
+    t.compile_fail("tests/28-group-typestate-conflict.rs");
+    t.compile_fail("tests/29-attribute-suggestions.rs");
+    t.pass("tests/30-inspect.rs");
+    t.pass("tests/31-no-std.rs");
+    t.pass("tests/32-const.rs");
+    t.compile_fail("tests/33-const-errors.rs");
+    t.pass("tests/34-boxed-setters.rs");
+    t.pass("tests/35-flatten.rs");
+    t.compile_fail("tests/36-flatten-collision.rs");
+    t.pass("tests/37-docs.rs");
+    t.pass("tests/38-try-into.rs");
+    t.compile_fail("tests/39-sub-builder-owned.rs");
+    t.compile_fail("tests/40-fn-builder-errors.rs");
+    t.compile_fail("tests/41-method-clash.rs");
+    t.compile_fail("tests/42-deprecated.rs");
+    t.compile_fail("tests/43-flatten-errors.rs");
 }